@@ -34,6 +34,16 @@ pub struct Cli {
     #[arg(long)]
     pub clean: bool,
 
+    /// Only document elements meant for the given audience.
+    ///
+    /// Services, methods, messages and fields can be limited to certain audiences by adding a
+    /// `@protomd:audience <names>` line to their comment, with a comma-separated list of audience
+    /// names. Elements without such a line are meant for every audience.
+    ///
+    /// If no audience is given, all elements are documented regardless of their audience.
+    #[arg(long)]
+    pub audience: Option<String>,
+
     /// Input files or folders to generate the documentation from.
     ///
     /// In case of a file, it is only included if it has a `*.proto` extension. However, if pointed
@@ -62,7 +72,11 @@ pub enum Command {
     /// file and then exit.
     Init,
 
+    /// Write the embedded templates into a directory, as starting point for custom templates.
+    #[command(args_conflicts_with_subcommands = true)]
     Templates {
+        #[command(subcommand)]
+        cmd: Option<TemplatesCommand>,
         /// Force creating files if the target directory isn't empty.
         #[arg(long, short)]
         force: bool,
@@ -79,6 +93,54 @@ pub enum Command {
         dir: PathBuf,
     },
 
+    /// Print all methods that use the given message or enum on STDOUT.
+    ///
+    /// This includes methods that take or return the type directly, as well as methods that
+    /// reference it through any field of their input or output, no matter how deeply nested. The
+    /// methods are printed as gRPC paths, one per line, like `/acme.users.v1.UserService/GetUser`.
+    Usages {
+        /// Directories that will be searched for referenced schema files.
+        #[arg(long, short = 'I', value_hint = ValueHint::DirPath)]
+        include: Vec<PathBuf>,
+        /// Fully qualified name of the message or enum, like `acme.users.v1.User`.
+        name: String,
+        /// Input files or folders to search for methods.
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+    },
+
+    /// Print all deprecated elements of the schema on STDOUT, as JSON.
+    ///
+    /// The report lists deprecated files, services, methods, messages, enums, fields and enum
+    /// values, together with their location in the schema and the reason of the deprecation, if
+    /// the comment contains any. The same data is available to pages as `deprecations`.
+    Deprecations {
+        /// Directories that will be searched for referenced schema files.
+        #[arg(long, short = 'I', value_hint = ValueHint::DirPath)]
+        include: Vec<PathBuf>,
+        /// Input files or folders to search for deprecated elements.
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+    },
+
+    /// Print the dependency graph of the schema on STDOUT, in the Graphviz DOT format.
+    ///
+    /// The graph covers packages, files, services, messages and enums. Files are connected by
+    /// their imports, services by the input and output types of their methods, and messages by
+    /// the types of their fields. Render it with Graphviz, like `protomd graph ... | dot -Tsvg`.
+    Graph {
+        /// Directories that will be searched for referenced schema files.
+        #[arg(long, short = 'I', value_hint = ValueHint::DirPath)]
+        include: Vec<PathBuf>,
+        /// Only show elements of packages matching the glob pattern, like `acme.users.*`. Can be
+        /// given multiple times. All packages are shown by default.
+        #[arg(long, short)]
+        package: Vec<String>,
+        /// Input files or folders to build the graph from.
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+    },
+
     /// Print the schema of the template context on STDOUT.
     ///
     /// This documents the structure of the data that is provided to the Jinja template when it is
@@ -111,6 +173,16 @@ pub enum Command {
     },
 }
 
+#[derive(Subcommand)]
+pub enum TemplatesCommand {
+    /// Check the configured templates for errors.
+    ///
+    /// Every template in the configured template directory is compiled, and the pages are
+    /// rendered against a built-in sample schema. Any access to undefined variables is treated as
+    /// error.
+    Check,
+}
+
 pub fn completion(dir: &Path) -> io::Result<()> {
     fs::create_dir_all(dir).ok();
 
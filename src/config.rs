@@ -15,14 +15,53 @@ pub struct Config {
     /// frontmatter = "outline: [2, 4]"
     #[serde(default)]
     pub frontmatter: String,
+    /// Optional directory to load templates from.
+    ///
+    /// By default, the embedded templates are used to render Markdown files. However, if this
+    /// setting is used the templates are loaded from the file system at the given directory
+    /// instead. Any template not found in the directory falls back to the embedded one.
+    ///
+    /// The embedded templates can always be referred to with a `protomd/` prefix. That allows to
+    /// only override single blocks of them, like `{% extends "protomd/package.md.j2" %}`.
     #[serde(default)]
     pub templates: Option<String>,
+    /// Treat any access to undefined variables in templates as error, instead of silently
+    /// rendering them as empty value.
+    ///
+    /// This helps to find typos in custom templates. Checks with `is defined` and the `default`
+    /// filter still work for undefined values.
+    #[serde(default)]
+    pub strict_undefined: bool,
+    /// How deprecated elements are documented.
+    ///
+    /// Elements count as deprecated if they have the `deprecated` option or a `@deprecated` tag in
+    /// their comment. Hiding them also hides everything that is defined in a deprecated file.
+    #[serde(default)]
+    pub deprecated: Deprecated,
     /// Configuration for `markdownlint`.
     #[serde(default)]
     pub markdownlint: Markdownlint,
     /// Symbols used to define the gRPC method type in templates.
     #[serde(default)]
     pub request_symbols: RequestSymbols,
+    /// Rules to limit which packages, services and methods are documented.
+    #[serde(default)]
+    pub filter: Filter,
+    /// Layout and naming of the generated files.
+    #[serde(default)]
+    pub output: Output,
+    /// Mermaid diagrams of the relationships between messages and enums.
+    #[serde(default)]
+    pub diagrams: Diagrams,
+    /// Errors that methods can return.
+    #[serde(default)]
+    pub errors: Errors,
+    /// Normalization of the Markdown in comments.
+    #[serde(default)]
+    pub comments: Comments,
+    /// Templates used to render each kind of output file.
+    #[serde(default)]
+    pub pages: Pages,
 }
 
 /// Configuration for `markdownlint`.
@@ -55,6 +94,267 @@ pub struct RequestSymbols {
     pub bidi_streaming: Option<String>,
 }
 
+/// Rules to limit which packages, services and methods are documented.
+///
+/// Patterns are globs matched against the fully qualified name of an element, like
+/// `acme.users.v1` (package), `acme.users.v1.UserService` (service) or
+/// `acme.users.v1.UserService.GetUser` (method). A rule matching an element applies to all its
+/// children as well.
+///
+/// Single services and methods can be hidden by adding a `@protomd:hide` line to their comment.
+#[derive(Clone, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Filter {
+    /// Only include matching elements. Everything is included if the list is empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Exclude matching elements, even if they are matched by the `include` list.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// How deprecated elements are documented.
+#[derive(Clone, Copy, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum Deprecated {
+    /// Document them like any other element, with a note about the deprecation.
+    #[default]
+    Show,
+    /// Collapse their documentation in a `<details>` block, so only the note about the
+    /// deprecation is visible at first.
+    Collapse,
+    /// Leave them out of the documentation entirely, including the messages and enums that are
+    /// referenced by methods.
+    Hide,
+}
+
+/// Layout and naming of the generated files.
+#[derive(Clone, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Output {
+    /// How the documentation is split into files.
+    #[serde(default)]
+    pub layout: Layout,
+    /// Template for the path of each generated file, relative to the output directory.
+    ///
+    /// The path must stay within the output directory, and each file can only document services
+    /// of a single package.
+    ///
+    /// The following variables are available:
+    ///
+    /// - `package`: the package name, like `acme.users.v1`.
+    /// - `package_path`: the package name as directories, like `acme/users/v1`.
+    /// - `service`: the service name (only for the `service` layout). Packages without services use
+    ///   `types` instead.
+    /// - `file`: the schema file path without extension, like `acme/users/v1/users` (only for the
+    ///   `file` layout).
+    ///
+    /// The default depends on the layout, being either `{{ package }}.md`,
+    /// `{{ package }}.{{ service }}.md` or `{{ file }}.md`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+}
+
+/// How the documentation is split into files.
+#[derive(Clone, Copy, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// One file per Protobuf package.
+    #[default]
+    Package,
+    /// One file per gRPC service.
+    Service,
+    /// One file per Protobuf schema file.
+    File,
+}
+
+/// Mermaid diagrams of the relationships between messages and enums, and of the message flow of
+/// methods.
+///
+/// By default, the diagrams are rendered as `mermaid` code blocks, which need a plugin to show up
+/// in Vitepress, like `vitepress-plugin-mermaid`.
+#[derive(Clone, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Diagrams {
+    /// How the diagrams are embedded into the Markdown files.
+    #[serde(default)]
+    pub flavor: DiagramFlavor,
+    /// Kind of the generated diagrams.
+    #[serde(default)]
+    pub kind: DiagramKind,
+    /// Add a diagram of the input and output types to each method.
+    #[serde(default)]
+    pub methods: bool,
+    /// Add a diagram of all types to the types section of each package.
+    #[serde(default)]
+    pub packages: bool,
+    /// Add a sequence diagram to methods, showing the flow of requests and responses.
+    #[serde(default)]
+    pub sequence: Sequence,
+    /// Maximum number of types in a single diagram. Any further types are left out to keep large
+    /// graphs readable, and a note about the omitted types is added instead (a comment for entity
+    /// relationship diagrams, as they don't support notes). Set to `0` to disable the limit.
+    #[serde(default = "Diagrams::default_max_types")]
+    pub max_types: usize,
+}
+
+impl Diagrams {
+    fn default_max_types() -> usize {
+        20
+    }
+}
+
+impl Default for Diagrams {
+    fn default() -> Self {
+        Self {
+            flavor: DiagramFlavor::default(),
+            kind: DiagramKind::default(),
+            methods: false,
+            packages: false,
+            sequence: Sequence::default(),
+            max_types: Self::default_max_types(),
+        }
+    }
+}
+
+/// How Mermaid diagrams are embedded into the Markdown files.
+#[derive(Clone, Copy, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum DiagramFlavor {
+    /// Fenced `mermaid` code block, as supported by GitHub, GitLab and Vitepress with the Mermaid
+    /// plugin.
+    #[default]
+    Fence,
+    /// Custom `::: mermaid` container, as supported by Azure DevOps wikis.
+    Container,
+    /// HTML `<pre class="mermaid">` element, for sites that load Mermaid by themselves.
+    Html,
+}
+
+/// Methods that get a sequence diagram.
+#[derive(Clone, Copy, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum Sequence {
+    /// No method.
+    #[default]
+    None,
+    /// Only methods with client-side, server-side or bidirectional streaming.
+    Streaming,
+    /// All methods.
+    All,
+}
+
+/// Kind of Mermaid diagram.
+#[derive(Clone, Copy, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum DiagramKind {
+    /// Class diagram, listing the fields of each message.
+    #[default]
+    Class,
+    /// Entity relationship diagram, listing the scalar fields of each message.
+    Er,
+}
+
+/// Errors that methods can return.
+///
+/// Errors are documented as list in the method comment, starting with an `Errors:` line that's
+/// followed by items like `- NOT_FOUND: The user doesn't exist.`. Alternatively, they can be
+/// defined with a custom method option.
+#[derive(Clone, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Errors {
+    /// Full name of a custom method option that lists the errors of a method.
+    ///
+    /// The option can be a message or a repeated message with a `code` field (a `google.rpc.Code`
+    /// enum, number or string) and a `description` or `message` field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option: Option<String>,
+}
+
+/// Normalization of the Markdown in comments, before it's placed into the generated files.
+///
+/// Comments are copied into the Markdown files as they are by default. Each of the following
+/// steps helps to fit them into the surrounding document.
+#[derive(Clone, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Comments {
+    /// Demote headings, so the highest one is one level below the heading of the documented
+    /// element and doesn't break the outline of the document. Headings that would end up below
+    /// level 6 (and any headings in field descriptions) are turned into bold text.
+    #[serde(default)]
+    pub demote_headings: bool,
+    /// Escape Vue interpolations like `{{ name }}`, which Vitepress would try to evaluate
+    /// otherwise. Code is left alone.
+    #[serde(default)]
+    pub escape_vue: bool,
+    /// Escape all `<` characters outside of code, so generics like `List<T>` aren't mistaken for
+    /// HTML tags. Note that this disables any intended HTML in comments as well.
+    #[serde(default)]
+    pub escape_html: bool,
+    /// Indent nested list items and their continuation lines by the width of their parent's
+    /// marker, as required by `CommonMark`, and separate lists from a preceding paragraph.
+    #[serde(default)]
+    pub fix_lists: bool,
+    /// Join hard-wrapped lines of paragraphs and list items into single lines.
+    #[serde(default)]
+    pub reflow: bool,
+}
+
+/// Templates used to render each kind of output file.
+///
+/// Template names are looked up in the `templates` directory, or in the embedded templates if
+/// not set. Besides the templates listed here, a template set can contain any amount of partial
+/// templates to be included by others. The embedded templates use `header.md.j2`,
+/// `method.md.j2`, `message.md.j2`, `type.md.j2`, `diagram.md.j2` and `tags.md.j2`.
+#[derive(Clone, Default, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Pages {
+    /// Template for each documentation file, as laid out by the `output` settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Optional index page, rendered once with a list of all packages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<Page>,
+    /// Optional page for each service, in addition to the documentation files.
+    ///
+    /// The page is rendered like a documentation file that contains only the service, so the
+    /// package template works for it as well. Its `path` is a template that can use `package`,
+    /// `package_path` and `service`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<Page>,
+    /// Optional page for each top-level message and enum, in addition to the documentation files.
+    ///
+    /// The page is rendered like a documentation file that contains only the type, so the
+    /// package template works for it as well. Its `path` is a template that can use `package`,
+    /// `package_path` and `message`. Links to the type still point to the documentation files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<Page>,
+    /// Additional pages, each rendered once with the same data as the index page.
+    ///
+    /// This allows to write guides that combine hand-written text with generated content, like a
+    /// list of all streaming methods. The templates are looked up in the `templates` directory
+    /// and can include the embedded `header.md.j2` to share the frontmatter and `markdownlint`
+    /// settings. The embedded `deprecations.md.j2` lists all deprecated elements.
+    #[serde(default)]
+    pub extra: Vec<Page>,
+}
+
+/// A single page that is rendered once.
+#[derive(Clone, Deserialize, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Page {
+    /// Name of the template to render.
+    pub template: String,
+    /// Path of the generated file, relative to the output directory.
+    pub path: String,
+}
+
 #[derive(Debug, Snafu)]
 pub enum LoadError {
     Build { source: config::ConfigError },
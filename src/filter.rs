@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use protox::prost_reflect::{
+    FileDescriptor, MethodDescriptor, ServiceDescriptor,
+    prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorProto},
+};
+
+use crate::config;
+
+/// Prefix of all comment lines that carry instructions for protomd.
+const MARKER_PREFIX: &str = "@protomd:";
+/// Comment marker that excludes a single element from the documentation.
+const HIDE_MARKER: &str = "hide";
+/// Comment marker that limits an element to a comma-separated list of audiences.
+const AUDIENCE_MARKER: &str = "audience";
+/// Comment tag that marks an element deprecated, in addition to the `deprecated` option.
+const DEPRECATED_TAG: &str = "@deprecated";
+
+/// Decides which elements end up in the generated documentation.
+pub struct Filter {
+    include: GlobSet,
+    exclude: GlobSet,
+    audience: Option<String>,
+    hide_deprecated: bool,
+    /// Full names of the types that are only used by filtered methods.
+    hidden_types: HashSet<String>,
+}
+
+impl Filter {
+    pub fn new(
+        config: &config::Filter,
+        deprecated: config::Deprecated,
+        audience: Option<String>,
+    ) -> Result<Self, globset::Error> {
+        Ok(Self {
+            include: build_set(&config.include)?,
+            exclude: build_set(&config.exclude)?,
+            audience,
+            hide_deprecated: matches!(deprecated, config::Deprecated::Hide),
+            hidden_types: HashSet::new(),
+        })
+    }
+
+    /// Hide the types with the given full names, as they are only used by filtered methods.
+    pub fn hide_types(&mut self, names: HashSet<String>) {
+        self.hidden_types = names;
+    }
+
+    /// Whether a package without any services should be documented, for its types.
+    pub fn package(&self, name: &str) -> bool {
+        !self.is_excluded(&[name]) && self.is_included(&[name])
+    }
+
+    /// Whether the type isn't only used by filtered methods.
+    pub fn is_type_used(&self, full_name: &str) -> bool {
+        !self.hidden_types.contains(full_name)
+    }
+
+    /// Whether the service should be documented, which is the case if it is not excluded itself
+    /// and at least one of its methods is documented. A service that matches the include rules
+    /// includes all its methods, unless they're excluded or hidden themselves.
+    pub fn service(&self, value: &ServiceDescriptor) -> bool {
+        let file = value.parent_file();
+        let names = [file.package_name(), value.full_name()];
+
+        !self.is_excluded(&names)
+            && self.is_visible(&file, value.path())
+            && value.methods().any(|m| self.method(&m))
+    }
+
+    /// Whether the method should be documented.
+    pub fn method(&self, value: &MethodDescriptor) -> bool {
+        let file = value.parent_file();
+        let service = value.parent_service();
+        let names = [file.package_name(), service.full_name(), value.full_name()];
+
+        !self.is_excluded(&names)
+            && self.is_visible(&file, value.path())
+            && self.is_included(&names)
+    }
+
+    /// Whether the element at the given path is neither hidden, nor limited to audiences other
+    /// than the selected one, according to the markers in its leading comments.
+    ///
+    /// Audience markers are ignored if no audience was selected. If deprecated elements are
+    /// hidden, the element must neither be deprecated itself nor be defined in a deprecated file.
+    pub fn is_visible(&self, file: &FileDescriptor, path: &[i32]) -> bool {
+        let proto = file.file_descriptor_proto();
+        if self.hide_deprecated
+            && (has_deprecated_option(proto, path) || has_deprecated_option(proto, &[]))
+        {
+            return false;
+        }
+
+        let Some(location) = proto
+            .source_code_info
+            .as_ref()
+            .and_then(|info| info.location.iter().find(|l| l.path == path))
+        else {
+            return true;
+        };
+
+        let comments = location.leading_comments();
+        if self.hide_deprecated && has_deprecated_tag(comments) {
+            return false;
+        }
+
+        markers(comments).all(|(name, value)| match name {
+            HIDE_MARKER => false,
+            AUDIENCE_MARKER => self
+                .audience
+                .as_ref()
+                .is_none_or(|audience| value.split(',').any(|v| v.trim() == audience)),
+            _ => true,
+        })
+    }
+
+    /// Whether any of the names is matched by the include rules. Everything is included if there
+    /// are no rules.
+    fn is_included(&self, names: &[&str]) -> bool {
+        self.include.is_empty() || names.iter().any(|name| self.include.is_match(name))
+    }
+
+    /// Whether any of the names is matched by the exclude rules.
+    fn is_excluded(&self, names: &[&str]) -> bool {
+        names.iter().any(|name| self.exclude.is_match(name))
+    }
+}
+
+pub fn build_set(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+
+    builder.build()
+}
+
+/// Whether the line is a comment that only contains a protomd marker.
+pub fn is_marker_line(line: &str) -> bool {
+    line.trim()
+        .strip_prefix("//")
+        .is_some_and(|comment| comment.trim_start().starts_with(MARKER_PREFIX))
+}
+
+/// Remove all protomd marker lines from the comment.
+pub fn strip_markers(comment: &str) -> String {
+    comment
+        .lines()
+        .filter(|line| !line.trim_start().starts_with(MARKER_PREFIX))
+        .fold(String::with_capacity(comment.len()), |mut acc, line| {
+            acc.push_str(line);
+            acc.push('\n');
+            acc
+        })
+}
+
+/// Whether the element at the given path within the file has the `deprecated` option set, or the
+/// file itself for an empty path. Elements don't inherit the option of their file here.
+///
+/// This is the only place that reads the option, so the documentation and the report of
+/// deprecations agree on what is deprecated.
+pub fn has_deprecated_option(file: &FileDescriptorProto, path: &[i32]) -> bool {
+    fn message(value: &DescriptorProto, path: &[i32]) -> Option<bool> {
+        match path {
+            [] => value.options.as_ref()?.deprecated,
+            [2, field] => {
+                value
+                    .field
+                    .get(*field as usize)?
+                    .options
+                    .as_ref()?
+                    .deprecated
+            }
+            [6, extension] => {
+                value
+                    .extension
+                    .get(*extension as usize)?
+                    .options
+                    .as_ref()?
+                    .deprecated
+            }
+            [3, nested, rest @ ..] => message(value.nested_type.get(*nested as usize)?, rest),
+            [4, nested, rest @ ..] => enumeration(value.enum_type.get(*nested as usize)?, rest),
+            _ => None,
+        }
+    }
+
+    fn enumeration(value: &EnumDescriptorProto, path: &[i32]) -> Option<bool> {
+        match path {
+            [] => value.options.as_ref()?.deprecated,
+            [2, variant] => {
+                value
+                    .value
+                    .get(*variant as usize)?
+                    .options
+                    .as_ref()?
+                    .deprecated
+            }
+            _ => None,
+        }
+    }
+
+    let element = match path {
+        [] => file.options.as_ref().and_then(|o| o.deprecated),
+        [4, index, rest @ ..] => file
+            .message_type
+            .get(*index as usize)
+            .and_then(|m| message(m, rest)),
+        [5, index, rest @ ..] => file
+            .enum_type
+            .get(*index as usize)
+            .and_then(|e| enumeration(e, rest)),
+        [6, index] => file
+            .service
+            .get(*index as usize)
+            .and_then(|s| s.options.as_ref()?.deprecated),
+        [6, index, 2, method] => file
+            .service
+            .get(*index as usize)
+            .and_then(|s| s.method.get(*method as usize)?.options.as_ref()?.deprecated),
+        [7, index] => file
+            .extension
+            .get(*index as usize)
+            .and_then(|e| e.options.as_ref()?.deprecated),
+        _ => None,
+    };
+
+    element.unwrap_or(false)
+}
+
+/// Whether the comment contains a `@deprecated` tag on any of its lines.
+fn has_deprecated_tag(comment: &str) -> bool {
+    comment.lines().any(|line| {
+        line.trim_start()
+            .strip_prefix(DEPRECATED_TAG)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    })
+}
+
+/// Iterate over all markers in the comment as name and (possibly empty) value pair.
+fn markers(comment: &str) -> impl Iterator<Item = (&str, &str)> {
+    comment.lines().filter_map(|line| {
+        let marker = line.trim().strip_prefix(MARKER_PREFIX)?;
+        Some(
+            marker
+                .split_once(char::is_whitespace)
+                .unwrap_or((marker, "")),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_full_names() -> Result<(), globset::Error> {
+        let filter = Filter::new(
+            &config::Filter {
+                include: vec![],
+                exclude: vec![
+                    "*.internal.*".to_owned(),
+                    "acme.admin.v1.AdminService".to_owned(),
+                ],
+            },
+            config::Deprecated::Show,
+            None,
+        )?;
+
+        assert!(filter.is_included(&["acme.users.v1"]));
+        assert!(filter.is_excluded(&["acme.internal.v1"]));
+        assert!(filter.is_excluded(&["acme.admin.v1", "acme.admin.v1.AdminService"]));
+        assert!(!filter.is_excluded(&["acme.admin.v1", "acme.admin.v1.OtherService"]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_markers() {
+        let comment = " Some text.\n\n @protomd:audience internal, partner\n @protomd:hide\n";
+
+        assert_eq!(
+            vec![("audience", "internal, partner"), ("hide", "")],
+            markers(comment).collect::<Vec<_>>()
+        );
+        assert_eq!(" Some text.\n\n", strip_markers(comment));
+        assert!(is_marker_line("  // @protomd:hide"));
+        assert!(!is_marker_line("  // Some text."));
+    }
+
+    #[test]
+    fn find_deprecated_tag() {
+        assert!(has_deprecated_tag(
+            " Old.\n @deprecated use `New` instead.\n"
+        ));
+        assert!(has_deprecated_tag(" @deprecated\n"));
+        assert!(!has_deprecated_tag(" @deprecatedness\n"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Export of the schema dependency graph in the Graphviz DOT format.
+
+use std::fmt::Write;
+
+use globset::GlobSet;
+use itertools::Itertools;
+use protox::prost_reflect::{DescriptorPool, FileDescriptor, Kind, MessageDescriptor};
+
+/// Render the graph of all packages, files, services, messages and enums in the pool.
+///
+/// Files are connected by their imports, services by the input and output types of their methods
+/// and messages by the types of their fields. Only elements of packages matching the `packages`
+/// patterns are part of the graph, or all if there are no patterns.
+pub fn render(pool: &DescriptorPool, packages: &GlobSet) -> String {
+    let included =
+        |file: &FileDescriptor| packages.is_empty() || packages.is_match(file.package_name());
+
+    let files = pool.files().filter(included).collect::<Vec<_>>();
+    let messages = files
+        .iter()
+        .flat_map(|f| f.messages().flat_map(|m| nested_messages(&m)))
+        .filter(|m| !m.is_map_entry())
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    writeln!(out, "digraph protomd {{").ok();
+    writeln!(out, "  rankdir=LR;").ok();
+    writeln!(out, "  node [shape=box];").ok();
+
+    let by_package = files.iter().sorted_by_key(|f| f.package_name());
+    for (package, files) in &by_package.chunk_by(|f| f.package_name()) {
+        writeln!(out).ok();
+        writeln!(
+            out,
+            "  subgraph {} {{",
+            quote(&format!("cluster_{package}"))
+        )
+        .ok();
+        writeln!(out, "    label={};", quote(package)).ok();
+
+        for file in files {
+            writeln!(out, "    {} [shape=note];", quote(file.name())).ok();
+
+            for service in file.services() {
+                writeln!(out, "    {} [shape=component];", quote(service.full_name())).ok();
+            }
+            for message in file.messages().flat_map(|m| nested_messages(&m)) {
+                if !message.is_map_entry() {
+                    writeln!(out, "    {};", quote(message.full_name())).ok();
+                }
+                for value in message.child_enums() {
+                    writeln!(out, "    {} [shape=ellipse];", quote(value.full_name())).ok();
+                }
+            }
+            for value in file.enums() {
+                writeln!(out, "    {} [shape=ellipse];", quote(value.full_name())).ok();
+            }
+        }
+
+        writeln!(out, "  }}").ok();
+    }
+
+    writeln!(out).ok();
+
+    for file in &files {
+        for import in file.dependencies().filter(included) {
+            writeln!(
+                out,
+                "  {} -> {} [style=dashed];",
+                quote(file.name()),
+                quote(import.name())
+            )
+            .ok();
+        }
+    }
+
+    for service in files.iter().flat_map(FileDescriptor::services) {
+        for method in service.methods() {
+            let (input, output) = (method.input(), method.output());
+            let types = if input == output {
+                vec![input]
+            } else {
+                vec![input, output]
+            };
+
+            for ty in types {
+                if included(&ty.parent_file()) {
+                    writeln!(
+                        out,
+                        "  {} -> {} [label={}];",
+                        quote(service.full_name()),
+                        quote(ty.full_name()),
+                        quote(method.name())
+                    )
+                    .ok();
+                }
+            }
+        }
+    }
+
+    for message in &messages {
+        for field in message.fields() {
+            let field_kind = match field.kind() {
+                Kind::Message(m) if m.is_map_entry() => m.map_entry_value_field().kind(),
+                kind => kind,
+            };
+            let (name, file) = match field_kind {
+                Kind::Message(m) => (m.full_name().to_owned(), m.parent_file()),
+                Kind::Enum(e) => (e.full_name().to_owned(), e.parent_file()),
+                _ => continue,
+            };
+
+            if included(&file) {
+                writeln!(
+                    out,
+                    "  {} -> {} [label={}];",
+                    quote(message.full_name()),
+                    quote(&name),
+                    quote(field.name())
+                )
+                .ok();
+            }
+        }
+    }
+
+    writeln!(out, "}}").ok();
+    out
+}
+
+/// Collect the message and all messages nested within it, recursively.
+fn nested_messages(message: &MessageDescriptor) -> Vec<MessageDescriptor> {
+    let mut messages = vec![message.clone()];
+    for nested in message.child_messages() {
+        messages.extend(nested_messages(&nested));
+    }
+    messages
+}
+
+/// Quote an identifier or label for DOT.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_ids() {
+        assert_eq!(r#""a.b""#, quote("a.b"));
+        assert_eq!(r#""a \"b\" \\c""#, quote(r#"a "b" \c"#));
+    }
+}
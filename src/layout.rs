@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use minijinja::{Environment, ErrorKind, Value, context};
+use protox::prost_reflect::{FileDescriptor, ServiceDescriptor};
+
+use crate::config;
+
+/// Decides which file each service is documented in, based on the configured layout, and where
+/// the separate service and message pages go.
+pub struct Layout {
+    kind: config::Layout,
+    env: Environment<'static>,
+}
+
+impl Layout {
+    pub fn new(config: &config::Output, pages: &config::Pages) -> Result<Self, minijinja::Error> {
+        let template = config.file_name.clone().unwrap_or_else(|| {
+            match config.layout {
+                config::Layout::Package => "{{ package }}.md",
+                config::Layout::Service => "{{ package }}.{{ service }}.md",
+                config::Layout::File => "{{ file }}.md",
+            }
+            .to_owned()
+        });
+
+        let mut env = Environment::new();
+        env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        env.add_template_owned("file_name", template)?;
+        if let Some(page) = &pages.service {
+            env.add_template_owned("service_page", page.path.clone())?;
+        }
+        if let Some(page) = &pages.message {
+            env.add_template_owned("message_page", page.path.clone())?;
+        }
+
+        Ok(Self {
+            kind: config.layout,
+            env,
+        })
+    }
+
+    /// Get the path of the file that documents the service, relative to the output directory.
+    /// Services with the same path are grouped together into a single file.
+    pub fn path(&self, service: &ServiceDescriptor) -> Result<String, minijinja::Error> {
+        self.render(&service.parent_file(), service.name())
+    }
+
+    /// Get the path of the file that documents the types of a package without any services,
+    /// relative to the output directory. The `service` layout uses `types` as service name.
+    pub fn types_path(&self, file: &FileDescriptor) -> Result<String, minijinja::Error> {
+        self.render(file, "types")
+    }
+
+    /// Get the path of the separate page of the service, if service pages are enabled.
+    pub fn service_page(
+        &self,
+        service: &ServiceDescriptor,
+    ) -> Result<Option<String>, minijinja::Error> {
+        let package = service.package_name();
+        self.render_page(
+            "service_page",
+            context! {
+                package,
+                package_path => package.replace('.', "/"),
+                service => service.name(),
+            },
+        )
+    }
+
+    /// Get the path of the separate page of a top-level message or enum, if message pages are
+    /// enabled.
+    pub fn message_page(
+        &self,
+        package: &str,
+        message: &str,
+    ) -> Result<Option<String>, minijinja::Error> {
+        self.render_page(
+            "message_page",
+            context! {
+                package,
+                package_path => package.replace('.', "/"),
+                message,
+            },
+        )
+    }
+
+    fn render_page(&self, name: &str, ctx: Value) -> Result<Option<String>, minijinja::Error> {
+        match self.env.get_template(name) {
+            Ok(template) => template.render(ctx).map(Some),
+            Err(e) if e.kind() == ErrorKind::TemplateNotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn render(&self, file: &FileDescriptor, service: &str) -> Result<String, minijinja::Error> {
+        let package = file.package_name();
+
+        let ctx = match self.kind {
+            config::Layout::Package => context! {
+                package,
+                package_path => package.replace('.', "/"),
+            },
+            config::Layout::Service => context! {
+                package,
+                package_path => package.replace('.', "/"),
+                service,
+            },
+            config::Layout::File => context! {
+                package,
+                package_path => package.replace('.', "/"),
+                file => Path::new(file.name()).with_extension("").to_string_lossy(),
+            },
+        };
+
+        self.env.get_template("file_name")?.render(ctx)
+    }
+}
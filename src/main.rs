@@ -1,14 +1,20 @@
 mod cli;
 mod config;
+mod filter;
+mod graph;
+mod layout;
+mod logger;
 mod resolver;
 mod templates;
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
-    fs::{self, File, FileType},
-    io::{BufWriter, Write},
-    path::{Path, PathBuf},
+    error::Error as _,
+    fs::{self, FileType},
+    io,
+    path::{Component, Path, PathBuf},
+    slice,
 };
 
 use indexmap::IndexSet;
@@ -16,31 +22,56 @@ use itertools::Itertools;
 use log::warn;
 use protox::{
     Compiler,
-    file::{ChainFileResolver, FileMetadata, IncludeFileResolver},
-    prost_reflect::FileDescriptor,
+    file::{
+        ChainFileResolver, FileMetadata, FileResolver, GoogleFileResolver, IncludeFileResolver,
+    },
+    prost_reflect::{FileDescriptor, ServiceDescriptor},
 };
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use schemars::schema_for;
+use serde::Serialize;
 use snafu::{ResultExt, Snafu, whatever};
 use walkdir::WalkDir;
 
 use self::{
-    cli::{Cli, Command},
+    cli::{Cli, Command, TemplatesCommand},
     config::Config,
-    resolver::CachingFileResolver,
-    templates::Package,
+    filter::Filter,
+    layout::Layout,
+    resolver::{CachingFileResolver, SourceFileResolver},
+    templates::{Deprecation, Overview, Package, PageKind, Site, Usages},
 };
 
 type Result<T, E = snafu::Whatever> = std::result::Result<T, E>;
 
 #[snafu::report]
 fn main() -> Result<()> {
+    logger::init();
     let cli = Cli::parse();
 
     if let Some(cmd) = cli.cmd {
         match cmd {
             Command::Init => init()?,
-            Command::Templates { dir, force } => templates(&dir, force)?,
+            Command::Templates {
+                cmd: Some(TemplatesCommand::Check),
+                ..
+            } => check_templates()?,
+            Command::Templates {
+                cmd: None,
+                dir,
+                force,
+            } => templates(&dir, force)?,
+            Command::Usages {
+                include,
+                name,
+                input,
+            } => usages(include, &name, input)?,
+            Command::Deprecations { include, input } => deprecations(include, input)?,
+            Command::Graph {
+                include,
+                package,
+                input,
+            } => graph(include, &package, input)?,
             Command::Schema => schema()?,
             Command::Completion { dir } => {
                 cli::completion(&dir).whatever_context("failed writing shell completions")?;
@@ -55,69 +86,302 @@ fn main() -> Result<()> {
 
     let config = config::load().whatever_context("failed loading configuration")?;
 
-    let packages = collect(cli.include, cli.input, &config)?;
+    let (packages, pages, deprecations, usages) = collect(
+        &build_resolver(cli.include),
+        search_inputs(cli.input)?,
+        cli.audience,
+        &config,
+    )?;
     render(
         cli.clean,
         &cli.output_dir,
-        packages,
-        config.templates.as_deref(),
+        &packages,
+        &pages,
+        &deprecations,
+        &usages,
+        &config,
     )?;
 
     Ok(())
 }
 
-fn collect(include: Vec<PathBuf>, input: Vec<PathBuf>, config: &Config) -> Result<Vec<Package>> {
-    let resolver = build_resolver(include);
-    let files = search_inputs(input)?;
-
-    let compiler = {
-        let mut c = Compiler::with_file_resolver(resolver.clone());
-        c.include_imports(true);
-        c.include_source_info(true);
-        c.open_files(files)
-            .whatever_context("failed opening Protobuf files")?;
-        c
-    };
+fn collect<R: FileResolver + 'static>(
+    resolver: &CachingFileResolver<R>,
+    files: impl IntoIterator<Item = impl AsRef<Path>>,
+    audience: Option<String>,
+    config: &Config,
+) -> Result<Collected> {
+    let mut filter = Filter::new(&config.filter, config.deprecated, audience)
+        .whatever_context("invalid filter pattern")?;
+    let layout = Layout::new(&config.output, &config.pages)
+        .whatever_context("invalid file name template")?;
+
+    let compiler = compile(resolver, files)?;
+    let pool = compiler.descriptor_pool();
+
+    if let Some(option) = &config.errors.option
+        && pool.get_extension_by_name(option).is_none()
+    {
+        warn!("errors option `{option}` isn't defined in any of the schema files");
+    }
 
     let metadata = compiler
         .files()
         .map(|f| (f.name(), f))
         .collect::<HashMap<_, _>>();
 
-    let packages = compiler
-        .descriptor_pool()
+    let files = pool
         .files()
         .filter(|file| should_generate(&metadata, file))
-        .into_group_map_by(|file| file.package_name().to_owned());
+        .collect::<Vec<_>>();
+
+    let deprecations = templates::deprecations(&files, &filter);
+    filter.hide_types(templates::filtered_types(&files, &filter));
+
+    let documents = documents(&files, &filter, &layout)?;
+
+    let usages = Usages::new(
+        documents
+            .iter()
+            .sorted_unstable_by(|(a, _), (b, _)| a.cmp(b))
+            .flat_map(|(path, entries)| {
+                entries
+                    .iter()
+                    .filter_map(|(_, service)| service.as_ref())
+                    .flat_map(ServiceDescriptor::methods)
+                    .filter(|method| filter.method(method))
+                    .map(move |method| (method, path.clone()))
+            }),
+    );
+
+    let mut files = files
+        .into_iter()
+        .map(|file| (file.package_name().to_owned(), file))
+        .into_group_map();
 
-    let templates = packages
+    let mut pages = Vec::new();
+    let mut templates = documents
         .into_iter()
-        .map(|(name, files)| Package::new(config.clone(), &resolver, name, &files))
+        .sorted_unstable_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(path, entries)| {
+            check_path(&path)?;
+
+            let name = entries[0].0.clone();
+            if let Some((other, _)) = entries.iter().find(|(package, _)| *package != name) {
+                whatever!(
+                    "file name `{path}` is used by the packages `{name}` and `{other}`, but a \
+                     file can only document a single package"
+                );
+            }
+
+            let services = entries
+                .into_iter()
+                .filter_map(|(_, service)| service)
+                .collect::<Vec<_>>();
+
+            // Types of a package are listed in its first file only.
+            let files = files.remove(&name).unwrap_or_default();
+            pages.append(&mut separate_pages(
+                config, resolver, &filter, &layout, &name, &services, &files,
+            )?);
+
+            Package::new(
+                config.clone(),
+                resolver,
+                &filter,
+                name,
+                path,
+                &services,
+                &files,
+            )
+        })
         .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(templates)
+    templates.retain(|package| !package.is_empty());
+    pages.sort_unstable_by(|a, b| a.path().cmp(b.path()));
+
+    let mut paths = templates.iter().map(Package::path).collect::<HashSet<_>>();
+    for page in &pages {
+        check_path(page.path())?;
+        if !paths.insert(page.path()) {
+            whatever!("page path `{}` is used by more than one file", page.path());
+        }
+    }
+
+    templates::index_usages(&mut templates, &usages);
+    templates::index_usages(&mut pages, &usages);
+
+    for reference in templates::link_references(&mut templates, &mut pages, &pool, &usages) {
+        warn!("{reference}");
+    }
+
+    Ok((templates, pages, deprecations, usages))
+}
+
+/// Documentation files, separate service and message pages, deprecated elements and type usages
+/// found in the schema.
+type Collected = (Vec<Package>, Vec<Package>, Vec<Deprecation>, Usages);
+
+/// Make sure a generated file stays within the output directory.
+fn check_path(path: &str) -> Result<()> {
+    let relative = Path::new(path);
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| matches!(c, Component::ParentDir))
+    {
+        whatever!("file name `{path}` must be relative to and within the output directory");
+    }
+
+    Ok(())
+}
+
+/// Build the separate pages of the services and top-level types of a documentation file, as far
+/// as they're enabled in the configuration.
+fn separate_pages(
+    config: &Config,
+    resolver: &impl FileResolver,
+    filter: &Filter,
+    layout: &Layout,
+    name: &str,
+    services: &[ServiceDescriptor],
+    files: &[FileDescriptor],
+) -> Result<Vec<Package>> {
+    let mut pages = Vec::new();
+
+    for service in services {
+        if let Some(path) = layout
+            .service_page(service)
+            .whatever_context("failed rendering service page path")?
+        {
+            let page = Package::new(
+                config.clone(),
+                resolver,
+                filter,
+                name.to_owned(),
+                path,
+                slice::from_ref(service),
+                &[],
+            )?;
+            pages.push(page.into_service_page());
+        }
+    }
+
+    if config.pages.message.is_some() && !files.is_empty() {
+        let types = Package::new(
+            config.clone(),
+            resolver,
+            filter,
+            name.to_owned(),
+            String::new(),
+            &[],
+            files,
+        )?;
+        let mut messages = types
+            .into_message_pages(|message| {
+                layout
+                    .message_page(name, message)
+                    .map(Option::unwrap_or_default)
+            })
+            .whatever_context("failed rendering message page path")?;
+        pages.append(&mut messages);
+    }
+
+    Ok(pages)
+}
+
+/// Package name and service (if any) to be documented in a file.
+type Document = (String, Option<ServiceDescriptor>);
+
+/// Group the documented services by the path of the file that documents them, along with their
+/// package name.
+fn documents(
+    files: &[FileDescriptor],
+    filter: &Filter,
+    layout: &Layout,
+) -> Result<HashMap<String, Vec<Document>>> {
+    let services = files
+        .iter()
+        .flat_map(|file| file.services().collect::<Vec<_>>())
+        .filter(|service| filter.service(service))
+        .collect::<Vec<_>>();
+
+    // Packages without any services still get a file for their types, unless they're filtered.
+    let types_only = files
+        .iter()
+        .into_group_map_by(|file| file.package_name())
+        .into_iter()
+        .filter(|(package, files)| {
+            files.iter().all(|file| file.services().len() == 0) && filter.package(package)
+        })
+        .map(|(_, files)| files[0])
+        .sorted_unstable_by_key(|file| file.package_name())
+        .map(|file| {
+            Ok((
+                layout.types_path(file)?,
+                (file.package_name().to_owned(), None),
+            ))
+        });
+
+    let documents = services
+        .iter()
+        .map(|service| {
+            Ok((
+                layout.path(service)?,
+                (service.package_name().to_owned(), Some(service.clone())),
+            ))
+        })
+        .chain(types_only)
+        .collect::<Result<Vec<_>, minijinja::Error>>()
+        .whatever_context("failed rendering file name")?
+        .into_iter()
+        .into_group_map();
+
+    Ok(documents)
+}
+
+fn compile<R: FileResolver + 'static>(
+    resolver: &CachingFileResolver<R>,
+    files: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Compiler> {
+    let mut compiler = Compiler::with_file_resolver(resolver.clone());
+    compiler.include_imports(true);
+    compiler.include_source_info(true);
+    compiler
+        .open_files(files)
+        .whatever_context("failed opening Protobuf files")?;
+
+    Ok(compiler)
 }
 
 #[derive(Debug, Snafu)]
 enum RenderError {
-    #[snafu(display("failed creating output file at {path:?}"))]
-    Create {
+    #[snafu(display("failed creating output directory at {path:?}"))]
+    CreateDir {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[snafu(display("failed writing output file at {path:?}"))]
+    Write {
         source: std::io::Error,
         path: PathBuf,
     },
+    #[snafu(display("failed rendering {name} into {path:?}"))]
     Render {
         source: templates::RenderError,
-    },
-    Flush {
-        source: std::io::Error,
+        name: String,
+        path: PathBuf,
     },
 }
 
 fn render(
     clean: bool,
     output_dir: &Path,
-    templates: Vec<Package>,
-    template_dir: Option<&str>,
+    templates: &[Package],
+    pages: &[Package],
+    deprecations: &[Deprecation],
+    usages: &Usages,
+    config: &Config,
 ) -> Result<()> {
     if clean {
         clean_output(output_dir)?;
@@ -125,30 +389,93 @@ fn render(
 
     fs::create_dir_all(output_dir).ok();
 
-    let env = templates::Env::new(template_dir)?;
-
+    let mut env = templates::Env::new(config.templates.as_deref(), Site::new(templates, usages));
+    if config.strict_undefined {
+        env = env.strict();
+    }
     templates
-        .into_par_iter()
+        .par_iter()
+        .chain(pages)
         .try_for_each(|template| {
-            let path = output_dir.join(template.file_name());
-            let file = File::create(&path).context(CreateSnafu { path })?;
-            let mut file = BufWriter::with_capacity(256 * 1024, file);
-
-            env.render(template, &mut file).context(RenderSnafu)?;
-            file.flush().context(FlushSnafu)?;
-
-            Ok::<_, RenderError>(())
+            render_file(
+                &env,
+                template_name(config, template.kind()),
+                &describe(template),
+                &template.current(),
+                template,
+                &output_dir.join(template.path()),
+            )
         })
         .whatever_context("failed rendering files")?;
 
+    for page in config.pages.index.iter().chain(&config.pages.extra) {
+        let overview = Overview::new(config, &page.path, templates, deprecations);
+        render_file(
+            &env,
+            &page.template,
+            &format!("page `{}`", page.template),
+            &overview.current(),
+            &overview,
+            &output_dir.join(&page.path),
+        )
+        .whatever_context("failed rendering pages")?;
+    }
+
     Ok(())
 }
 
+/// Name of the template that renders the documentation file or separate page.
+fn template_name(config: &Config, kind: PageKind) -> &str {
+    let page = match kind {
+        PageKind::Package => {
+            return config
+                .pages
+                .package
+                .as_deref()
+                .unwrap_or(templates::PACKAGE_TEMPLATE);
+        }
+        PageKind::Service => &config.pages.service,
+        PageKind::Message => &config.pages.message,
+    };
+
+    page.as_ref()
+        .map_or(templates::PACKAGE_TEMPLATE, |page| &page.template)
+}
+
+/// Describe the documentation file or separate page for error messages.
+fn describe(template: &Package) -> String {
+    match template.kind() {
+        PageKind::Package => format!("package `{}`", template.name()),
+        PageKind::Service | PageKind::Message => format!("page `{}`", template.path()),
+    }
+}
+
+fn render_file(
+    env: &templates::Env,
+    template: &str,
+    name: &str,
+    current: &templates::Current<'_>,
+    ctx: impl Serialize,
+    path: &Path,
+) -> Result<(), RenderError> {
+    // Render into memory first, to not leave partial files behind when rendering fails.
+    let mut content = Vec::with_capacity(256 * 1024);
+    env.render(template, current, ctx, &mut content)
+        .context(RenderSnafu { name, path })?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(CreateDirSnafu { path: parent })?;
+    }
+
+    fs::write(path, content).context(WriteSnafu { path })
+}
+
 fn build_resolver(includes: Vec<PathBuf>) -> CachingFileResolver<ChainFileResolver> {
     let mut chain = ChainFileResolver::new();
     for include in includes {
         chain.add(IncludeFileResolver::new(include));
     }
+    chain.add(GoogleFileResolver::new());
 
     CachingFileResolver::new(chain)
 }
@@ -216,7 +543,7 @@ fn clean_output(path: &Path) -> Result<()> {
 }
 
 fn should_generate(metadata: &HashMap<&str, &FileMetadata>, file: &FileDescriptor) -> bool {
-    metadata.get(file.name()).is_some_and(|m| !m.is_import()) && file.services().count() > 0
+    metadata.get(file.name()).is_some_and(|m| !m.is_import())
 }
 
 fn init() -> Result<()> {
@@ -241,12 +568,168 @@ fn templates(dir: &Path, force: bool) -> Result<()> {
     }
 
     fs::create_dir_all(dir).whatever_context("failed creating output directory")?;
-    fs::write(
-        dir.join("package.md.j2"),
-        include_str!("../templates/package.md.j2").as_bytes(),
-    )
-    .whatever_context("failed writing template file")?;
+    for (name, source) in templates::EMBEDDED {
+        fs::write(dir.join(name), source.as_bytes())
+            .whatever_context("failed writing template file")?;
+    }
+
+    Ok(())
+}
+
+fn check_templates() -> Result<()> {
+    let config = config::load().whatever_context("failed loading configuration")?;
+    let template_dir = config.templates.as_deref();
+
+    let sample = Config {
+        filter: config::Filter::default(),
+        errors: config::Errors::default(),
+        ..config.clone()
+    };
+    let (packages, pages, deprecations, usages) = collect(
+        &CachingFileResolver::new(SourceFileResolver::new(
+            "sample.proto",
+            include_str!("sample.proto"),
+        )),
+        ["sample.proto"],
+        None,
+        &sample,
+    )?;
+
+    let env = templates::Env::new(template_dir, Site::new(&packages, &usages)).strict();
+    let mut errors = Vec::new();
+
+    let mut names = templates::EMBEDDED
+        .iter()
+        .map(|(name, _)| (*name).to_owned())
+        .collect::<IndexSet<_>>();
+    if let Some(dir) = template_dir {
+        names.extend(find_templates(Path::new(dir))?);
+    }
+
+    for name in &names {
+        if let Err(e) = env.check(name) {
+            errors.push(e);
+        }
+    }
+
+    for package in packages.iter().chain(&pages) {
+        let template = template_name(&config, package.kind());
+        if let Err(e) = env.render_raw(template, &package.current(), package, io::sink()) {
+            errors.push(e);
+        }
+    }
+
+    for page in config.pages.index.iter().chain(&config.pages.extra) {
+        let overview = Overview::new(&sample, &page.path, &packages, &deprecations);
+        if let Err(e) = env.render_raw(&page.template, &overview.current(), &overview, io::sink()) {
+            errors.push(e);
+        }
+    }
+
+    for error in &errors {
+        eprintln!("error: {error}");
+
+        let mut source = error.source();
+        while let Some(cause) = source {
+            eprintln!("  caused by: {cause}");
+            source = cause.source();
+        }
+
+        eprintln!("{}", templates::debug_info(error));
+    }
+
+    if !errors.is_empty() {
+        whatever!("found {} errors in the templates", errors.len());
+    }
+
+    println!("checked {} templates", names.len());
+    Ok(())
+}
+
+/// Find all template files in the directory, as names relative to it.
+fn find_templates(dir: &Path) -> Result<Vec<String>> {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|entry| {
+            entry
+                .map(|entry| {
+                    let is_template = entry.file_type().is_file()
+                        && entry.path().extension().is_some_and(|ext| ext == "j2");
+
+                    is_template
+                        .then(|| {
+                            entry.path().strip_prefix(dir).ok().map(|path| {
+                                path.components()
+                                    .filter_map(|c| c.as_os_str().to_str())
+                                    .join("/")
+                            })
+                        })
+                        .flatten()
+                })
+                .whatever_context("invalid entry")
+                .transpose()
+        })
+        .collect()
+}
+
+fn usages(include: Vec<PathBuf>, name: &str, input: Vec<PathBuf>) -> Result<()> {
+    let name = name.trim_start_matches('.');
+    let compiler = compile(&build_resolver(include), search_inputs(input)?)?;
+    let pool = compiler.descriptor_pool();
+
+    if pool.get_message_by_name(name).is_none() && pool.get_enum_by_name(name).is_none() {
+        whatever!("unknown message or enum `{name}`");
+    }
+
+    let metadata = compiler
+        .files()
+        .map(|f| (f.name(), f))
+        .collect::<HashMap<_, _>>();
+
+    // Filters only affect the documentation, but a change to the type affects all methods.
+    let usages = Usages::new(
+        pool.files()
+            .filter(|file| should_generate(&metadata, file))
+            .flat_map(|file| file.services().collect::<Vec<_>>())
+            .flat_map(|service| service.methods().collect::<Vec<_>>())
+            .map(|method| (method, String::new())),
+    );
+
+    for usage in usages.all(name) {
+        println!("{usage}");
+    }
+
+    Ok(())
+}
+
+fn deprecations(include: Vec<PathBuf>, input: Vec<PathBuf>) -> Result<()> {
+    let config = config::load().whatever_context("failed loading configuration")?;
+    // The report is meant to plan the removal, so it must list hidden elements as well.
+    let config = Config {
+        deprecated: config::Deprecated::Show,
+        ..config
+    };
+    let (_, _, deprecations, _) = collect(
+        &build_resolver(include),
+        search_inputs(input)?,
+        None,
+        &config,
+    )?;
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&deprecations)
+            .whatever_context("failed serializing deprecations")?
+    );
+    Ok(())
+}
+
+fn graph(include: Vec<PathBuf>, packages: &[String], input: Vec<PathBuf>) -> Result<()> {
+    let packages = filter::build_set(packages).whatever_context("invalid package pattern")?;
+    let compiler = compile(&build_resolver(include), search_inputs(input)?)?;
 
+    print!("{}", graph::render(&compiler.descriptor_pool(), &packages));
     Ok(())
 }
 
@@ -42,3 +42,29 @@ impl<T: FileResolver> FileResolver for CachingFileResolver<T> {
         }
     }
 }
+
+/// Resolver for a single schema file that is held in memory.
+pub struct SourceFileResolver {
+    name: &'static str,
+    source: &'static str,
+}
+
+impl SourceFileResolver {
+    pub fn new(name: &'static str, source: &'static str) -> Self {
+        Self { name, source }
+    }
+}
+
+impl FileResolver for SourceFileResolver {
+    fn resolve_path(&self, path: &Path) -> Option<String> {
+        (path == Path::new(self.name)).then(|| self.name.to_owned())
+    }
+
+    fn open_file(&self, name: &str) -> Result<protox::file::File, protox::Error> {
+        if name == self.name {
+            protox::file::File::from_source(name, self.source)
+        } else {
+            Err(protox::Error::file_not_found(name))
+        }
+    }
+}
@@ -1,63 +1,261 @@
-use std::io::Write;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error as _,
+    fmt,
+    io::Write,
+    ops::RangeInclusive,
+    sync::Arc,
+};
 
 use indexmap::IndexMap;
 use itertools::Itertools;
-use minijinja::Environment;
+use minijinja::{Environment, Value};
 use protox::{
     file::FileResolver,
     prost_reflect::{
-        EnumDescriptor, FieldDescriptor, FileDescriptor, Kind, MessageDescriptor, MethodDescriptor,
-        ServiceDescriptor,
+        DescriptorPool, EnumDescriptor, EnumValueDescriptor, ExtensionDescriptor, FieldDescriptor,
+        FileDescriptor, Kind, MessageDescriptor, MethodDescriptor, OneofDescriptor,
+        ServiceDescriptor, Syntax,
+        prost_types::{SourceCodeInfo, source_code_info::Location},
     },
 };
 use schemars::JsonSchema;
 use serde::Serialize;
 use snafu::{OptionExt, ResultExt, Snafu};
 
-use crate::Result;
-use crate::config;
+use self::references::{KnownNames, Linker, Unresolved};
+pub use self::{
+    deprecations::{Deprecation, collect as deprecations},
+    usages::{Usages, filtered_types},
+};
+use crate::{
+    Result, config,
+    filter::{self, Filter},
+};
+
+mod constraints;
+mod deprecations;
+mod diagram;
+mod errors;
+mod filters;
+mod normalize;
+mod references;
+mod tags;
+mod usages;
 
-mod filters {
-    pub fn slugify(s: String) -> String {
-        slug::slugify(s)
-    }
-}
+/// Templates that are embedded into the binary and used if no custom template directory is
+/// configured.
+pub const EMBEDDED: &[(&str, &str)] = &[
+    (
+        "deprecations.md.j2",
+        include_str!("../templates/deprecations.md.j2"),
+    ),
+    ("diagram.md.j2", include_str!("../templates/diagram.md.j2")),
+    ("header.md.j2", include_str!("../templates/header.md.j2")),
+    ("index.md.j2", include_str!("../templates/index.md.j2")),
+    ("message.md.j2", include_str!("../templates/message.md.j2")),
+    ("method.md.j2", include_str!("../templates/method.md.j2")),
+    ("package.md.j2", include_str!("../templates/package.md.j2")),
+    ("tags.md.j2", include_str!("../templates/tags.md.j2")),
+    ("type.md.j2", include_str!("../templates/type.md.j2")),
+];
+
+/// Name of the template to render each package with, if not configured otherwise.
+pub const PACKAGE_TEMPLATE: &str = "package.md.j2";
+
+/// Prefix to explicitly refer to an embedded template, even if a custom template with the same
+/// name exists. For example, `{% extends "protomd/package.md.j2" %}`.
+const EMBEDDED_PREFIX: &str = "protomd/";
+
+/// Name of the context variable that tells filters and functions which file is being rendered. It
+/// uses a reserved name, so variables of custom templates can't shadow it.
+const CURRENT_KEY: &str = "__protomd_current";
+
+/// Highest number a field can have, which is written as `max` in ranges.
+const FIELD_NUMBER_MAX: u32 = 536_870_911;
 
 pub struct Env(Environment<'static>);
 
+/// The file that is currently being rendered.
+#[derive(Serialize)]
+pub struct Current<'a> {
+    /// Name of the package that the file documents, if it's dedicated to a single package.
+    pub package: Option<&'a str>,
+    /// Path of the file, relative to the output directory.
+    pub path: &'a str,
+}
+
 #[derive(Debug, Snafu)]
 pub enum RenderError {
-    #[snafu(display("missing or invalid template"))]
+    #[snafu(display("missing or invalid template{}", debug_suffix(source)))]
     Resolve { source: minijinja::Error },
-    #[snafu(display("failed rendering"))]
+    #[snafu(display("failed rendering{}", debug_suffix(source)))]
     Render { source: minijinja::Error },
 }
 
+/// Get the debug information of the innermost template error, like from an included template.
+/// It contains an excerpt of the template source and the variables around the failure location.
+pub fn debug_info(error: &minijinja::Error) -> String {
+    let mut error = error;
+    while let Some(inner) = error
+        .source()
+        .and_then(|e| e.downcast_ref::<minijinja::Error>())
+    {
+        error = inner;
+    }
+
+    error.display_debug_info().to_string().trim_end().to_owned()
+}
+
+/// Format the debug information to be appended to an error message, if there is any.
+fn debug_suffix(error: &minijinja::Error) -> String {
+    let info = debug_info(error);
+    if info.is_empty() {
+        info
+    } else {
+        format!(":\n{info}")
+    }
+}
+
 impl Env {
-    pub fn new(template_dir: Option<&str>) -> Result<Self> {
+    /// Create a new template environment. The `site` is exposed to all templates and used to
+    /// resolve links between files.
+    pub fn new(template_dir: Option<&str>, site: Site) -> Self {
         let mut env = Environment::new();
+        env.set_debug(true);
         env.add_filter("slugify", filters::slugify);
+        env.add_filter("md_escape", filters::md_escape);
+        env.add_filter("table_escape", filters::table_escape);
+        env.add_filter("wrap", filters::wrap);
+        env.add_filter("package_path", filters::package_path);
+        env.add_filter("relative", filters::relative);
+        env.add_function("anchor", filters::anchor);
+        env.add_global("site", Value::from_serialize(&site));
 
-        if let Some(dir) = template_dir {
-            env.set_loader(minijinja::path_loader(dir));
-        } else {
-            env.add_template("package.md.j2", include_str!("../templates/package.md.j2"))
-                .whatever_context("invalid template")?;
-        }
+        let site = Arc::new(site);
+        env.add_filter("type_name", filters::type_name(Arc::clone(&site)));
+        env.add_function("link_to", filters::link_to(site));
 
-        Ok(Self(env))
+        env.set_loader(loader(template_dir));
+
+        Self(env)
+    }
+
+    /// Treat any access to undefined values as error, instead of rendering them empty.
+    pub fn strict(mut self) -> Self {
+        self.0
+            .set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        self
+    }
+
+    /// Load and compile the template, without rendering it.
+    pub fn check(&self, template: &str) -> Result<(), minijinja::Error> {
+        self.0.get_template(template).map(|_| ())
     }
 
-    pub fn render(&self, package: Package, writer: impl Write) -> Result<(), RenderError> {
+    /// Render the template, keeping the error as reported by the template engine.
+    pub fn render_raw(
+        &self,
+        template: &str,
+        current: &Current<'_>,
+        ctx: impl Serialize,
+        writer: impl Write,
+    ) -> Result<(), minijinja::Error> {
         self.0
-            .get_template("package.md.j2")
+            .get_template(template)?
+            .render_to_write(with_current(current, ctx), writer)
+            .map(|_| ())
+    }
+
+    pub fn render(
+        &self,
+        template: &str,
+        current: &Current<'_>,
+        ctx: impl Serialize,
+        writer: impl Write,
+    ) -> Result<(), RenderError> {
+        self.0
+            .get_template(template)
             .context(ResolveSnafu)?
-            .render_to_write(package, writer)
+            .render_to_write(with_current(current, ctx), writer)
             .map(|_| ())
             .context(RenderSnafu)
     }
 }
 
+/// Add the information about the current file to the template context.
+fn with_current(current: &Current<'_>, ctx: impl Serialize) -> Value {
+    let current = Value::from_serialize(current);
+    let ctx = Value::from_serialize(ctx);
+    minijinja::context! { __protomd_current => current, ..ctx }
+}
+
+/// Create a template loader that searches the custom template directory first (if any), and
+/// falls back to the embedded templates.
+fn loader(
+    template_dir: Option<&str>,
+) -> impl Fn(&str) -> Result<Option<String>, minijinja::Error> + Send + Sync + 'static {
+    let custom = template_dir.map(minijinja::path_loader);
+    let embedded = |name: &str| {
+        EMBEDDED
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, source)| (*source).to_owned())
+    };
+
+    move |name| {
+        if let Some(name) = name.strip_prefix(EMBEDDED_PREFIX) {
+            return Ok(embedded(name));
+        }
+
+        if let Some(custom) = &custom
+            && let Some(source) = custom(name)?
+        {
+            return Ok(Some(source));
+        }
+
+        Ok(embedded(name))
+    }
+}
+
+/// Overview of all generated packages, used to render pages that are not tied to a single
+/// package, like the index page.
+#[derive(Serialize)]
+pub struct Overview<'a> {
+    /// Configuration of `protomd` and not part of the schema definition.
+    config: &'a config::Config,
+    /// Path of the generated page, relative to the output directory.
+    path: &'a str,
+    /// All packages that documentation files are generated for.
+    packages: &'a [Package],
+    /// All deprecated elements of the schema, ordered by file and line.
+    deprecations: &'a [Deprecation],
+}
+
+impl<'a> Overview<'a> {
+    pub fn new(
+        config: &'a config::Config,
+        path: &'a str,
+        packages: &'a [Package],
+        deprecations: &'a [Deprecation],
+    ) -> Self {
+        Self {
+            config,
+            path,
+            packages,
+            deprecations,
+        }
+    }
+
+    /// Information about the page, to resolve links relative to it.
+    pub fn current(&self) -> Current<'_> {
+        Current {
+            package: None,
+            path: self.path,
+        }
+    }
+}
+
 /// A Protobuf package which contains services and methods. Maybe originate from multiple schema
 /// files.
 #[derive(JsonSchema, Serialize)]
@@ -66,30 +264,511 @@ pub struct Package {
     config: config::Config,
     /// The package name.
     name: String,
+    /// Path of the generated file, relative to the output directory.
+    path: String,
+    /// Kind of the generated file. Separate service and message pages contain only a single
+    /// service or type, and no diagram.
+    kind: PageKind,
+    /// Syntax of the schema files, if all files of this document use the same one. See
+    /// [`SchemaFile::syntax`] for the possible values.
+    syntax: Option<&'static str>,
+    /// Schema files that define the services and types of this document, ordered by name.
+    files: Vec<SchemaFile>,
     /// List of services defined in the package.
     services: Vec<Service>,
+    /// All messages and enums defined in the package, ordered by name. If the package is split
+    /// into several files, only the first one lists the types.
+    types: Vec<Type>,
+    /// Mermaid diagram of all types in the package, if enabled in the configuration.
+    diagram: Option<String>,
 }
 
 impl Package {
     pub fn new(
         config: config::Config,
         resolver: &impl FileResolver,
+        filter: &Filter,
         name: String,
-        value: &[FileDescriptor],
+        path: String,
+        value: &[ServiceDescriptor],
+        files: &[FileDescriptor],
     ) -> Result<Self> {
+        let mut types = files
+            .iter()
+            .flat_map(|f| {
+                f.messages()
+                    .map(CombinedDescriptor::from)
+                    .chain(f.enums().map(CombinedDescriptor::from))
+            })
+            .filter(|d| {
+                filter.is_visible(&d.parent_file(), d.path()) && filter.is_type_used(d.full_name())
+            })
+            .map(|d| Type::new(resolver, filter, &d))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .sorted_unstable_by(|a, b| a.name.cmp(&b.name))
+            .collect::<Vec<_>>();
+
+        let mut services = value
+            .iter()
+            .map(|v| Service::new(&config.errors, resolver, filter, v))
+            .collect::<Result<Vec<_>>>()?;
+
+        for service in &mut services {
+            service.normalize(&config.comments);
+        }
+        for ty in &mut types {
+            ty.normalize(&config.comments);
+        }
+
+        let diagrams = &config.diagrams;
+        if diagrams.methods {
+            for method in services.iter_mut().flat_map(|s| &mut s.methods) {
+                let types = method
+                    .input
+                    .iter()
+                    .chain(&method.output)
+                    .unique_by(|(name, _)| *name)
+                    .map(|(name, message)| (name.as_str(), message))
+                    .collect::<Vec<_>>();
+
+                method.diagram = Some(diagram::render(diagrams, &name, &types));
+            }
+        }
+
+        for service in &mut services {
+            for method in &mut service.methods {
+                let streaming = method.client_streaming || method.server_streaming;
+                let enabled = match diagrams.sequence {
+                    config::Sequence::None => false,
+                    config::Sequence::Streaming => streaming,
+                    config::Sequence::All => true,
+                };
+
+                if enabled {
+                    method.sequence = Some(diagram::sequence(&name, &service.name, method));
+                }
+            }
+        }
+
+        let schema_files = value
+            .iter()
+            .map(ServiceDescriptor::parent_file)
+            .chain(files.iter().cloned())
+            .unique_by(|f| f.name().to_owned())
+            .map(|f| SchemaFile::new(&f))
+            .sorted_unstable_by(|a, b| a.name.cmp(&b.name))
+            .collect::<Vec<_>>();
+        let syntax = schema_files.iter().map(|f| f.syntax).all_equal_value().ok();
+
+        let diagram = (diagrams.packages && !types.is_empty()).then(|| {
+            let mut all = Vec::new();
+            for ty in &types {
+                ty.flatten(&mut all);
+            }
+
+            let types = all
+                .into_iter()
+                .map(|ty| (ty.full_name.as_str(), &ty.message))
+                .collect::<Vec<_>>();
+
+            diagram::render(diagrams, &name, &types)
+        });
+
         Ok(Self {
             config,
             name,
-            services: value
+            path,
+            kind: PageKind::Package,
+            syntax,
+            files: schema_files,
+            services,
+            types,
+            diagram,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> PageKind {
+        self.kind
+    }
+
+    /// Turn the file into the separate page of its services, which is expected to be just one.
+    pub fn into_service_page(self) -> Self {
+        Self {
+            kind: PageKind::Service,
+            ..self
+        }
+    }
+
+    /// Split the types of the package into separate pages, one for each top-level type. The path
+    /// of each page is created from the name of its type.
+    pub fn into_message_pages<E>(
+        self,
+        mut path: impl FnMut(&str) -> Result<String, E>,
+    ) -> Result<Vec<Self>, E> {
+        let Self {
+            config,
+            name,
+            syntax,
+            files,
+            types,
+            ..
+        } = self;
+
+        types
+            .into_iter()
+            .map(|ty| {
+                Ok(Self {
+                    config: config.clone(),
+                    name: name.clone(),
+                    path: path(&ty.name)?,
+                    kind: PageKind::Message,
+                    syntax,
+                    files: files.clone(),
+                    services: Vec::new(),
+                    types: vec![ty],
+                    diagram: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the package neither has services nor types to document.
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty() && self.types.is_empty()
+    }
+
+    /// Describe the file of this package, for rendering it.
+    pub fn current(&self) -> Current<'_> {
+        Current {
+            package: Some(&self.name),
+            path: &self.path,
+        }
+    }
+}
+
+/// Kind of a generated file, which decides the template it is rendered with.
+#[derive(Clone, Copy, JsonSchema, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageKind {
+    /// Documentation file, as laid out by the `output` settings.
+    Package,
+    /// Separate page of a single service.
+    Service,
+    /// Separate page of a single top-level message or enum.
+    Message,
+}
+
+/// A Protobuf schema file that is part of a package.
+#[derive(Clone, JsonSchema, Serialize)]
+struct SchemaFile {
+    /// Path of the file, relative to its include directory.
+    name: String,
+    /// Syntax of the file, being either `proto2` or `proto3`. Files without a `syntax` statement
+    /// use `proto2`.
+    syntax: &'static str,
+}
+
+impl SchemaFile {
+    fn new(value: &FileDescriptor) -> Self {
+        let syntax = match value.file_descriptor_proto().syntax() {
+            "proto3" => "proto3",
+            _ => "proto2",
+        };
+
+        Self {
+            name: value.name().to_owned(),
+            syntax,
+        }
+    }
+}
+
+/// Turn references to messages and enums in all comments into links to their documentation.
+/// Returns the references that couldn't be resolved, ordered and without duplicates.
+///
+/// Links always point to the documentation files, also from the separate service and message
+/// pages.
+pub fn link_references(
+    packages: &mut [Package],
+    pages: &mut [Package],
+    pool: &DescriptorPool,
+    usages: &Usages,
+) -> Vec<Unresolved> {
+    let site = Site::new(packages, usages);
+    let names = KnownNames::new(pool);
+    let mut unresolved = Vec::new();
+
+    for package in packages.iter_mut().chain(pages) {
+        let mut linker = Linker::new(pool, &names, &site.types, &package.path);
+
+        for service in &mut package.services {
+            let scope = if package.name.is_empty() {
+                service.name.clone()
+            } else {
+                format!("{}.{}", package.name, service.name)
+            };
+            service.link(&mut linker, &scope);
+        }
+        for ty in &mut package.types {
+            ty.link(&mut linker);
+        }
+
+        unresolved.append(&mut linker.unresolved);
+    }
+
+    unresolved.sort_unstable();
+    unresolved.dedup();
+    unresolved
+}
+
+/// Fill the usage lists of all types, with the methods that use them directly as input or output
+/// and the ones that reach them through any of their fields.
+pub fn index_usages(packages: &mut [Package], usages: &Usages) {
+    for package in packages {
+        for ty in &mut package.types {
+            ty.set_usages(&package.path, usages);
+        }
+    }
+}
+
+/// Information about all generated files, available as global `site` variable in every
+/// template. It allows to refer to other packages and types, no matter which file is rendered.
+#[derive(Serialize)]
+pub struct Site {
+    /// Name and path of every generated package file, ordered by path.
+    packages: Vec<SitePackage>,
+    /// Location of the documentation of each message and enum, by its full name.
+    types: BTreeMap<String, TypeLocation>,
+    /// Methods that use each message and enum, directly or through any of the fields of their
+    /// input and output, by its full name. Paths are relative to the output directory.
+    usages: BTreeMap<String, Vec<Usage>>,
+}
+
+#[derive(Serialize)]
+struct SitePackage {
+    /// The package name.
+    name: String,
+    /// Path of the generated file, relative to the output directory.
+    path: String,
+}
+
+#[derive(Serialize)]
+struct TypeLocation {
+    /// Name of the package that defines the type.
+    package: String,
+    /// Path of the file the type is documented in, relative to the output directory.
+    path: String,
+    /// Anchor of the type within the file.
+    anchor: String,
+}
+
+impl Site {
+    /// Build the site information from all generated packages and the index of type usages.
+    ///
+    /// Types might be referenced from several files, in which case a file of the package that
+    /// defines the type is preferred.
+    pub fn new(packages: &[Package], usages: &Usages) -> Self {
+        // Whether the type is documented by its own package, its package and the path.
+        let mut types = HashMap::<&str, (bool, &str, &str)>::new();
+
+        for package in packages {
+            let mut defined = Vec::new();
+            for ty in &package.types {
+                ty.flatten(&mut defined);
+            }
+            for ty in defined {
+                types.insert(&ty.full_name, (true, &package.name, &package.path));
+            }
+        }
+
+        for package in packages {
+            let messages = package
+                .services
                 .iter()
-                .flat_map(FileDescriptor::services)
-                .map(|v| Service::new(resolver, &v))
-                .collect::<Result<_>>()?,
+                .flat_map(|s| &s.methods)
+                .flat_map(|m| m.input.iter().chain(&m.output));
+
+            for (name, message) in messages {
+                let own = message.package == package.name;
+
+                types
+                    .entry(name.as_str())
+                    .and_modify(|entry| {
+                        if own && !entry.0 {
+                            *entry = (own, &message.package, &package.path);
+                        }
+                    })
+                    .or_insert((own, &message.package, &package.path));
+            }
+        }
+
+        Self {
+            packages: packages
+                .iter()
+                .map(|p| SitePackage {
+                    name: p.name.clone(),
+                    path: p.path.clone(),
+                })
+                .collect(),
+            types: types
+                .into_iter()
+                .map(|(name, (_, package, path))| {
+                    let location = TypeLocation {
+                        package: package.to_owned(),
+                        path: path.to_owned(),
+                        anchor: filters::anchor(name),
+                    };
+                    (name.to_owned(), location)
+                })
+                .collect(),
+            usages: usages
+                .iter_all()
+                .map(|(name, usages)| (name.clone(), usages.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// A message or enum defined in a package, as part of the package's type reference.
+#[derive(JsonSchema, Serialize)]
+struct Type {
+    /// Name of the type, relative to the package. For nested types, this includes the names of
+    /// the parent messages, like `User.Address`.
+    name: String,
+    /// Fully qualified name of the type, including the package name.
+    full_name: String,
+    #[serde(flatten)]
+    message: Message,
+    /// Messages and enums nested within this type, ordered by name.
+    nested: Vec<Type>,
+    /// Methods that use this type directly as input or output.
+    used_by: Vec<Usage>,
+    /// Methods that use this type anywhere in their input or output, including through the fields
+    /// of other messages.
+    reached_by: Vec<Usage>,
+}
+
+impl Type {
+    fn new(
+        resolver: &impl FileResolver,
+        filter: &Filter,
+        value: &CombinedDescriptor,
+    ) -> Result<Self> {
+        let file = value.parent_file();
+        let nested = match value {
+            CombinedDescriptor::Message(d) => d
+                .child_messages()
+                .filter(|m| !m.is_map_entry())
+                .map(CombinedDescriptor::from)
+                .chain(d.child_enums().map(CombinedDescriptor::from))
+                .filter(|d| filter.is_visible(&file, d.path()))
+                .map(|d| Self::new(resolver, filter, &d))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .sorted_unstable_by(|a, b| a.name.cmp(&b.name))
+                .collect(),
+            CombinedDescriptor::Enum(_) => Vec::new(),
+        };
+
+        let full_name = value.full_name();
+        let name = full_name
+            .strip_prefix(file.package_name())
+            .and_then(|n| n.strip_prefix('.'))
+            .unwrap_or(full_name);
+
+        Ok(Self {
+            name: name.to_owned(),
+            full_name: full_name.to_owned(),
+            message: Message::new(resolver, filter, value)?,
+            nested,
+            used_by: Vec::new(),
+            reached_by: Vec::new(),
         })
     }
 
-    pub fn file_name(&self) -> String {
-        format!("{}.md", self.name)
+    /// Collect this type and all types nested in it, recursively.
+    fn flatten<'a>(&'a self, types: &mut Vec<&'a Type>) {
+        types.push(self);
+        for nested in &self.nested {
+            nested.flatten(types);
+        }
+    }
+
+    /// Set the methods that use this type and its nested types, with paths relative to the file
+    /// at `path`.
+    fn set_usages(&mut self, path: &str, usages: &Usages) {
+        let relative = |usages: &[Usage]| {
+            usages
+                .iter()
+                .map(|usage| Usage {
+                    path: if usage.path == path {
+                        String::new()
+                    } else {
+                        filters::relative_path(path, &usage.path)
+                    },
+                    ..usage.clone()
+                })
+                .collect()
+        };
+
+        self.used_by = relative(usages.direct(&self.full_name));
+        self.reached_by = relative(usages.all(&self.full_name));
+
+        for nested in &mut self.nested {
+            nested.set_usages(path, usages);
+        }
+    }
+
+    /// Turn references in the comments of this type and its nested types into links.
+    fn link(&mut self, linker: &mut Linker<'_>) {
+        self.message.link(linker, &self.full_name);
+        for nested in &mut self.nested {
+            nested.link(linker);
+        }
+    }
+
+    /// Normalize the comments of this type and its nested types, which are all placed below a
+    /// level 3 heading.
+    fn normalize(&mut self, config: &config::Comments) {
+        self.message.normalize(config, 3);
+        for nested in &mut self.nested {
+            nested.normalize(config);
+        }
+    }
+}
+
+/// Reference to a method that uses a type.
+#[derive(Clone, JsonSchema, Serialize)]
+pub struct Usage {
+    /// Name of the package the method belongs to.
+    package: String,
+    /// Name of the service the method belongs to.
+    service: String,
+    /// Name of the method.
+    method: String,
+    /// Path of the file that documents the method, relative to the file being rendered. Empty if
+    /// it's the same file.
+    path: String,
+    /// Anchor of the method within its file.
+    anchor: String,
+}
+
+impl fmt::Display for Usage {
+    /// Format the usage as gRPC method path, like `/acme.users.v1.UserService/GetUser`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.package.is_empty() {
+            write!(f, "/{}/{}", self.service, self.method)
+        } else {
+            write!(f, "/{}.{}/{}", self.package, self.service, self.method)
+        }
     }
 }
 
@@ -100,6 +779,8 @@ struct Service {
     name: String,
     /// Description of the gRPC service.
     description: String,
+    /// Structured tags of the description, like `@since` or `@see`.
+    tags: tags::Tags,
     /// List of methods the service provides.
     methods: Vec<Method>,
     /// Whether this service is marked deprecated.
@@ -109,7 +790,12 @@ struct Service {
 }
 
 impl Service {
-    fn new(resolver: &impl FileResolver, value: &ServiceDescriptor) -> Result<Self> {
+    fn new(
+        errors: &config::Errors,
+        resolver: &impl FileResolver,
+        filter: &Filter,
+        value: &ServiceDescriptor,
+    ) -> Result<Self> {
         let source = value
             .parent_file_descriptor_proto()
             .source_code_info
@@ -120,34 +806,61 @@ impl Service {
             .location
             .iter()
             .find(|l| l.path == value.path())
-            .map(|l| unindent::unindent(l.leading_comments().trim()))
+            .map(|l| description(l.leading_comments()))
             .unwrap_or_default();
+        let (description, tags) = description;
 
-        let deprecated = value
-            .service_descriptor_proto()
-            .options
-            .as_ref()
-            .and_then(|o| o.deprecated)
-            .unwrap_or(false);
-
-        let file_deprecated = value
-            .parent_file_descriptor_proto()
-            .options
-            .as_ref()
-            .and_then(|o| o.deprecated)
-            .unwrap_or(false);
+        let file = value.parent_file_descriptor_proto();
+        let deprecated = filter::has_deprecated_option(file, value.path()) || tags.deprecated();
+        let file_deprecated = filter::has_deprecated_option(file, &[]);
 
         Ok(Self {
             name: value.name().to_owned(),
             description,
+            tags,
             methods: value
                 .methods()
-                .map(|v| Method::new(resolver, &v))
+                .filter(|v| filter.method(v))
+                .map(|v| Method::new(errors, resolver, filter, &v))
                 .collect::<Result<_>>()?,
             deprecated,
             file_deprecated,
         })
     }
+
+    /// Turn references in the comments of this service and its methods into links.
+    fn link(&mut self, linker: &mut Linker<'_>, scope: &str) {
+        self.description = linker.link(scope, &self.description);
+        self.tags.link(linker, scope);
+
+        for method in &mut self.methods {
+            let scope = format!("{scope}.{}", method.name);
+            method.description = linker.link(&scope, &method.description);
+            method.tags.link(linker, &scope);
+
+            for (name, message) in method.input.iter_mut().chain(&mut method.output) {
+                message.link(linker, name);
+            }
+        }
+    }
+
+    /// Normalize the comments of this service and its methods, according to the heading levels
+    /// of the embedded templates.
+    fn normalize(&mut self, config: &config::Comments) {
+        self.description = normalize::normalize(config, &self.description, 2);
+        self.tags.normalize(config, 2);
+
+        for method in &mut self.methods {
+            method.description = normalize::normalize(config, &method.description, 4);
+            method.tags.normalize(config, 4);
+            for error in &mut method.errors {
+                error.normalize(config);
+            }
+            for message in method.input.values_mut().chain(method.output.values_mut()) {
+                message.normalize(config, 5);
+            }
+        }
+    }
 }
 
 /// A gRPC method as part of a service.
@@ -157,6 +870,8 @@ struct Method {
     name: String,
     /// Description of the method.
     description: String,
+    /// Structured tags of the description, like `@since` or `@see`.
+    tags: tags::Tags,
     /// Input method parameter to the method call.
     input: IndexMap<String, Message>,
     /// Output message parameter to the method call.
@@ -167,10 +882,22 @@ struct Method {
     server_streaming: bool,
     /// Whether this method is marked deprecated.
     deprecated: bool,
+    /// Errors the method can return, from the `Errors:` list of its comment and the configured
+    /// method option.
+    errors: Vec<errors::MethodError>,
+    /// Mermaid diagram of the input and output types, if enabled in the configuration.
+    diagram: Option<String>,
+    /// Mermaid sequence diagram of the requests and responses, if enabled in the configuration.
+    sequence: Option<String>,
 }
 
 impl Method {
-    fn new(resolver: &impl FileResolver, value: &MethodDescriptor) -> Result<Self> {
+    fn new(
+        errors: &config::Errors,
+        resolver: &impl FileResolver,
+        filter: &Filter,
+        value: &MethodDescriptor,
+    ) -> Result<Self> {
         let source = value.parent_file();
         let source = source
             .file_descriptor_proto()
@@ -182,24 +909,32 @@ impl Method {
             .location
             .iter()
             .find(|l| l.path == value.path())
-            .map(|l| unindent::unindent(l.leading_comments().trim()))
+            .map(|l| description(l.leading_comments()))
             .unwrap_or_default();
 
-        let deprecated = value
-            .method_descriptor_proto()
-            .options
-            .as_ref()
-            .and_then(|o| o.deprecated)
-            .unwrap_or(false);
+        let (description, tags) = description;
+        let (description, mut method_errors) = errors::split(&description);
+        if let Some(option) = &errors.option {
+            method_errors.extend(errors::parse_option(value, option));
+        }
+
+        let deprecated = filter::has_deprecated_option(
+            value.parent_file().file_descriptor_proto(),
+            value.path(),
+        ) || tags.deprecated();
 
         Ok(Self {
             name: value.name().to_owned(),
             description,
-            input: find_messages(resolver, value.input())?,
-            output: find_messages(resolver, value.output())?,
+            tags,
+            input: find_messages(resolver, filter, value.input())?,
+            output: find_messages(resolver, filter, value.output())?,
             client_streaming: value.is_client_streaming(),
             server_streaming: value.is_server_streaming(),
             deprecated,
+            errors: method_errors,
+            diagram: None,
+            sequence: None,
         })
     }
 }
@@ -207,16 +942,45 @@ impl Method {
 /// A Protobuf message, referenced by a method as parameter or nested within.
 #[derive(JsonSchema, Serialize)]
 struct Message {
+    /// Kind of the type, being either `message` or `enum`.
+    kind: &'static str,
+    /// Name of the package that defines the type.
+    package: String,
     /// Description of the message.
     description: String,
+    /// Structured tags of the description, like `@since` or `@see`.
+    tags: tags::Tags,
     /// Raw Protobuf schema definition.
     proto: String,
+    /// Fields of the message, in order of definition. Always empty for enums.
+    fields: Vec<Field>,
+    /// Oneofs of the message, in order of definition. Always empty for enums.
+    oneofs: Vec<Oneof>,
+    /// Values of the enum, in order of definition. Always empty for messages.
+    values: Vec<EnumValue>,
+    /// Extensions of the message, from `extend` blocks anywhere in the schema, ordered by number.
+    /// Always empty for enums.
+    extensions: Vec<Extension>,
+    /// Ranges of field numbers that are available for extensions. Always empty for enums.
+    extension_ranges: Vec<NumberRange>,
+    /// Ranges of field or value numbers that are reserved and must not be used.
+    reserved_ranges: Vec<NumberRange>,
+    /// Names of fields or values that are reserved and must not be used.
+    reserved_names: Vec<String>,
+    /// Whether the enum is closed, meaning unknown values are treated as unknown fields instead
+    /// of being kept in the field. This is the case for enums of `proto2` files. Always false for
+    /// messages.
+    closed: bool,
     /// Whether this message is marked deprecated.
     deprecated: bool,
 }
 
 impl Message {
-    fn new(resolver: &impl FileResolver, value: &CombinedDescriptor) -> Result<Self> {
+    fn new(
+        resolver: &impl FileResolver,
+        filter: &Filter,
+        value: &CombinedDescriptor,
+    ) -> Result<Self> {
         let source = value.parent_file();
         let file = resolver
             .open_file(source.name())
@@ -234,57 +998,512 @@ impl Message {
             .find(|l| l.path == value.path())
             .whatever_context("missing location for message")?;
 
-        let description = unindent::unindent(location.leading_comments().trim());
+        let (description, tags) = description(location.leading_comments());
         let proto = file
             .source()
             .map(|source| {
-                let start = location.span[0] as usize;
-                let end = if location.span.len() == 4 {
-                    location.span[2]
-                } else {
-                    location.span[0]
-                } as usize;
-
-                source
-                    .lines()
+                let (start, end) = line_span(location);
+                let lines = source.lines().collect::<Vec<_>>();
+                let hidden = hidden_lines(filter, value, source_info, &lines);
+
+                lines
+                    .iter()
+                    .enumerate()
                     .skip(start)
                     .take(end - start + 1)
+                    .filter(|(i, line)| {
+                        !hidden.iter().any(|range| range.contains(i))
+                            && !filter::is_marker_line(line)
+                    })
+                    .map(|(_, line)| *line)
                     .intersperse("\n")
                     .collect::<String>()
             })
             .unwrap_or_default();
-        let proto = unindent::unindent(&proto);
-        let deprecated = value.deprecated();
+        // `unindent` leaves the first line alone, so nested types would keep their indentation.
+        let proto = unindent::unindent(&format!("\n{proto}"))
+            .trim_start_matches('\n')
+            .to_owned();
+        let fields = value
+            .fields()
+            .filter(|f| filter.is_visible(&source, f.path()))
+            .map(|f| Field::new(source_info, &f))
+            .collect();
+        let oneofs = value
+            .oneofs()
+            .filter(|o| !o.is_synthetic() && filter.is_visible(&source, o.path()))
+            .map(|o| Oneof::new(source_info, filter, &o))
+            .collect();
+        let values = value
+            .values()
+            .filter(|v| filter.is_visible(&source, v.path()))
+            .map(|v| EnumValue::new(source_info, &v))
+            .collect();
+        let extensions = value
+            .extensions()
+            .filter(|e| filter.is_visible(&e.parent_file(), e.path()))
+            .map(|e| Extension::new(&e))
+            .sorted_unstable_by_key(|e| e.number)
+            .collect();
+        let (extension_ranges, reserved_ranges, reserved_names) = match value {
+            CombinedDescriptor::Message(d) => (
+                d.extension_ranges()
+                    .map(|r| NumberRange::new(r.start, r.end - 1, FIELD_NUMBER_MAX))
+                    .collect(),
+                d.reserved_ranges()
+                    .map(|r| NumberRange::new(r.start, r.end - 1, FIELD_NUMBER_MAX))
+                    .collect(),
+                d.reserved_names().map(str::to_owned).collect(),
+            ),
+            CombinedDescriptor::Enum(d) => (
+                Vec::new(),
+                d.reserved_ranges()
+                    .map(|r| NumberRange::new(*r.start(), *r.end(), i32::MAX))
+                    .collect(),
+                d.reserved_names().map(str::to_owned).collect(),
+            ),
+        };
+
+        let closed = matches!(value, CombinedDescriptor::Enum(_))
+            && source.file_descriptor_proto().syntax() == "proto2";
+        let deprecated = value.deprecated() || tags.deprecated();
 
         Ok(Self {
+            kind: match value {
+                CombinedDescriptor::Message(_) => "message",
+                CombinedDescriptor::Enum(_) => "enum",
+            },
+            package: source.package_name().to_owned(),
             description,
+            tags,
             proto,
+            fields,
+            oneofs,
+            values,
+            extensions,
+            extension_ranges,
+            reserved_ranges,
+            reserved_names,
+            closed,
             deprecated,
         })
     }
+
+    /// Turn references in the comments of this message and its members into links. The `scope`
+    /// is the full name of the message.
+    fn link(&mut self, linker: &mut Linker<'_>, scope: &str) {
+        self.description = linker.link(scope, &self.description);
+        self.tags.link(linker, scope);
+
+        for field in &mut self.fields {
+            field.description = linker.link(scope, &field.description);
+            field.tags.link(linker, scope);
+        }
+        for oneof in &mut self.oneofs {
+            oneof.description = linker.link(scope, &oneof.description);
+            oneof.tags.link(linker, scope);
+        }
+        for value in &mut self.values {
+            value.description = linker.link(scope, &value.description);
+            value.tags.link(linker, scope);
+        }
+        for extension in &mut self.extensions {
+            extension.description = linker.link(scope, &extension.description);
+            extension.tags.link(linker, scope);
+        }
+    }
+
+    /// Normalize the comments of this message and its members. The message is placed below a
+    /// heading of the given `level`, while its members are placed in tables or lists that can't
+    /// contain any headings.
+    fn normalize(&mut self, config: &config::Comments, level: usize) {
+        self.description = normalize::normalize(config, &self.description, level);
+        self.tags.normalize(config, level);
+        for field in &mut self.fields {
+            field.description = normalize::normalize(config, &field.description, 6);
+            field.tags.normalize(config, 6);
+        }
+        for oneof in &mut self.oneofs {
+            oneof.description = normalize::normalize(config, &oneof.description, 6);
+            oneof.tags.normalize(config, 6);
+        }
+        for value in &mut self.values {
+            value.description = normalize::normalize(config, &value.description, 6);
+            value.tags.normalize(config, 6);
+        }
+        for extension in &mut self.extensions {
+            extension.description = normalize::normalize(config, &extension.description, 6);
+            extension.tags.normalize(config, 6);
+        }
+    }
+}
+
+/// A single field of a message.
+#[derive(JsonSchema, Serialize)]
+struct Field {
+    /// Name of the field.
+    name: String,
+    /// Number that identifies the field in the binary encoding.
+    number: u32,
+    /// Description of the field.
+    description: String,
+    /// Structured tags of the description, like `@since` or `@see`.
+    tags: tags::Tags,
+    /// Type of the field. Either a scalar type like `uint32`, or the full name of a message or
+    /// enum. For maps, this is the type of the values.
+    #[serde(rename = "type")]
+    ty: String,
+    /// Type of the keys, if this field is a map.
+    map_key: Option<String>,
+    /// Label of the field, being either `repeated`, `optional` or `required`. Not set for
+    /// regular singular fields and maps.
+    label: Option<&'static str>,
+    /// Presence of the field, being either `explicit` if it tracks whether it was set, `implicit`
+    /// if an unset field can't be told apart from its default value, or `required`. Not set for
+    /// repeated fields and maps.
+    presence: Option<&'static str>,
+    /// Custom default value of the field, as set with the `default` option in `proto2`. Enum
+    /// values are given by name, and strings and bytes without quotes.
+    default: Option<String>,
+    /// Validation constraints of the field, as defined with the `buf.validate.field` option of
+    /// protovalidate, or the `validate.rules` option of `protoc-gen-validate`.
+    constraints: Vec<constraints::Constraint>,
+    /// Name of the oneof this field is part of, if any. Not set for `optional` fields in proto3,
+    /// which are wrapped in a synthetic oneof.
+    oneof: Option<String>,
+    /// Whether this field is marked deprecated, either with the `deprecated` option or a
+    /// `@deprecated` tag. Fields of a deprecated oneof aren't marked themselves.
+    deprecated: bool,
+}
+
+impl Field {
+    fn new(source_info: &SourceCodeInfo, value: &FieldDescriptor) -> Self {
+        let (description, tags) = find_description(source_info, value.path());
+
+        let (ty, map_key) = match value.kind() {
+            Kind::Message(m) if m.is_map_entry() => (
+                kind_name(&m.map_entry_value_field().kind()),
+                Some(kind_name(&m.map_entry_key_field().kind())),
+            ),
+            kind => (kind_name(&kind), None),
+        };
+
+        let proto = value.field_descriptor_proto();
+        let label = if value.is_map() {
+            None
+        } else if value.is_list() {
+            Some("repeated")
+        } else if value.is_required() {
+            Some("required")
+        } else if proto.proto3_optional()
+            || (value.parent_file().syntax() == Syntax::Proto2 && proto.label.is_some())
+        {
+            Some("optional")
+        } else {
+            None
+        };
+
+        let presence = if value.is_list() || value.is_map() {
+            None
+        } else if value.is_required() {
+            Some("required")
+        } else if value.supports_presence() {
+            Some("explicit")
+        } else {
+            Some("implicit")
+        };
+
+        let deprecated = filter::has_deprecated_option(
+            value.parent_file().file_descriptor_proto(),
+            value.path(),
+        ) || tags.deprecated();
+
+        Self {
+            name: value.name().to_owned(),
+            number: value.number(),
+            description,
+            tags,
+            ty,
+            map_key,
+            label,
+            presence,
+            default: proto.default_value.clone(),
+            constraints: constraints::parse(value),
+            oneof: value
+                .containing_oneof()
+                .filter(|o| !o.is_synthetic())
+                .map(|o| o.name().to_owned()),
+            deprecated,
+        }
+    }
+}
+
+/// A oneof of a message, of which at most one field can be set at a time.
+#[derive(JsonSchema, Serialize)]
+struct Oneof {
+    /// Name of the oneof.
+    name: String,
+    /// Description of the oneof.
+    description: String,
+    /// Structured tags of the description, like `@since` or `@see`.
+    tags: tags::Tags,
+    /// Names of the fields that are part of the oneof, in order of definition.
+    fields: Vec<String>,
+    /// Whether this oneof is marked deprecated. Oneofs have no `deprecated` option, so this is
+    /// only set by a `@deprecated` tag.
+    deprecated: bool,
+}
+
+impl Oneof {
+    fn new(source_info: &SourceCodeInfo, filter: &Filter, value: &OneofDescriptor) -> Self {
+        let (description, tags) = find_description(source_info, value.path());
+        let file = value.parent_message().parent_file();
+
+        Self {
+            name: value.name().to_owned(),
+            fields: value
+                .fields()
+                .filter(|f| filter.is_visible(&file, f.path()))
+                .map(|f| f.name().to_owned())
+                .collect(),
+            deprecated: tags.deprecated(),
+            description,
+            tags,
+        }
+    }
+}
+
+/// A field that extends a message from an `extend` block, which might be defined in another file
+/// or package than the message itself.
+#[derive(JsonSchema, Serialize)]
+struct Extension {
+    /// Fully qualified name of the extension, like `acme.audit.v1.created_by`.
+    full_name: String,
+    /// Number that identifies the extension in the binary encoding.
+    number: u32,
+    /// Description of the extension.
+    description: String,
+    /// Structured tags of the description, like `@since` or `@see`.
+    tags: tags::Tags,
+    /// Type of the extension. Either a scalar type like `uint32`, or the full name of a message
+    /// or enum.
+    #[serde(rename = "type")]
+    ty: String,
+    /// Label of the extension, being either `repeated` or `optional`. Not set for extensions in
+    /// `proto3` files.
+    label: Option<&'static str>,
+    /// Path of the schema file that defines the extension.
+    file: String,
+    /// Whether this extension is marked deprecated, either with the `deprecated` option or a
+    /// `@deprecated` tag.
+    deprecated: bool,
+}
+
+impl Extension {
+    fn new(value: &ExtensionDescriptor) -> Self {
+        let (description, tags) = value
+            .parent_file_descriptor_proto()
+            .source_code_info
+            .as_ref()
+            .map(|info| find_description(info, value.path()))
+            .unwrap_or_default();
+
+        let proto = value.field_descriptor_proto();
+        let label = if value.is_list() {
+            Some("repeated")
+        } else if value.parent_file().syntax() == Syntax::Proto2 && proto.label.is_some() {
+            Some("optional")
+        } else {
+            None
+        };
+
+        let deprecated =
+            filter::has_deprecated_option(value.parent_file_descriptor_proto(), value.path())
+                || tags.deprecated();
+
+        Self {
+            full_name: value.full_name().to_owned(),
+            number: value.number(),
+            description,
+            tags,
+            ty: kind_name(&value.kind()),
+            label,
+            file: value.parent_file().name().to_owned(),
+            deprecated,
+        }
+    }
+}
+
+/// A range of field or enum value numbers, with both ends included.
+#[derive(JsonSchema, Serialize)]
+struct NumberRange {
+    /// First number of the range.
+    start: i64,
+    /// Last number of the range.
+    end: i64,
+    /// The range as written in the schema, like `5`, `100 to 199` or `1000 to max`.
+    text: String,
+}
+
+impl NumberRange {
+    fn new(start: impl Into<i64>, end: impl Into<i64>, max: impl Into<i64>) -> Self {
+        let (start, end) = (start.into(), end.into());
+        let text = if start == end {
+            start.to_string()
+        } else if end == max.into() {
+            format!("{start} to max")
+        } else {
+            format!("{start} to {end}")
+        };
+
+        Self { start, end, text }
+    }
+}
+
+/// A single value of an enum.
+#[derive(JsonSchema, Serialize)]
+struct EnumValue {
+    /// Name of the value.
+    name: String,
+    /// Number that represents the value in the binary encoding.
+    number: i32,
+    /// Description of the value.
+    description: String,
+    /// Structured tags of the description, like `@since` or `@see`.
+    tags: tags::Tags,
+    /// Whether this value is marked deprecated, either with the `deprecated` option or a
+    /// `@deprecated` tag.
+    deprecated: bool,
+}
+
+impl EnumValue {
+    fn new(source_info: &SourceCodeInfo, value: &EnumValueDescriptor) -> Self {
+        let (description, tags) = find_description(source_info, value.path());
+        let deprecated = filter::has_deprecated_option(
+            value.parent_file().file_descriptor_proto(),
+            value.path(),
+        ) || tags.deprecated();
+
+        Self {
+            name: value.name().to_owned(),
+            number: value.number(),
+            description,
+            tags,
+            deprecated,
+        }
+    }
+}
+
+/// Get the Protobuf type name for the kind of a field.
+fn kind_name(kind: &Kind) -> String {
+    match kind {
+        Kind::Double => "double",
+        Kind::Float => "float",
+        Kind::Int32 => "int32",
+        Kind::Int64 => "int64",
+        Kind::Uint32 => "uint32",
+        Kind::Uint64 => "uint64",
+        Kind::Sint32 => "sint32",
+        Kind::Sint64 => "sint64",
+        Kind::Fixed32 => "fixed32",
+        Kind::Fixed64 => "fixed64",
+        Kind::Sfixed32 => "sfixed32",
+        Kind::Sfixed64 => "sfixed64",
+        Kind::Bool => "bool",
+        Kind::String => "string",
+        Kind::Bytes => "bytes",
+        Kind::Message(m) => return m.full_name().to_owned(),
+        Kind::Enum(e) => return e.full_name().to_owned(),
+    }
+    .to_owned()
+}
+
+/// Turn raw leading comments into a description, without any protomd markers, and split off its
+/// structured tags.
+fn description(comments: &str) -> (String, tags::Tags) {
+    tags::split(&unindent::unindent(filter::strip_markers(comments).trim()))
+}
+
+/// Find the leading comments of the element at `path` and turn them into a description.
+fn find_description(source_info: &SourceCodeInfo, path: &[i32]) -> (String, tags::Tags) {
+    source_info
+        .location
+        .iter()
+        .find(|l| l.path == path)
+        .map(|l| description(l.leading_comments()))
+        .unwrap_or_default()
+}
+
+/// Get the zero-based start and end line of a source location.
+fn line_span(location: &Location) -> (usize, usize) {
+    let start = location.span[0] as usize;
+    let end = if location.span.len() == 4 {
+        location.span[2]
+    } else {
+        location.span[0]
+    } as usize;
+
+    (start, end)
+}
+
+/// Find the source lines of all elements nested within the message/enum that are not visible,
+/// including the comment lines directly above them.
+fn hidden_lines(
+    filter: &Filter,
+    value: &CombinedDescriptor,
+    source_info: &SourceCodeInfo,
+    lines: &[&str],
+) -> Vec<RangeInclusive<usize>> {
+    let file = value.parent_file();
+    let mut paths = Vec::new();
+    value.nested_paths(&mut paths);
+
+    paths
+        .into_iter()
+        .filter(|path| !filter.is_visible(&file, path))
+        .filter_map(|path| source_info.location.iter().find(|l| l.path == path))
+        .map(|location| {
+            let (mut start, end) = line_span(location);
+            while start > 0 && lines[start - 1].trim_start().starts_with("//") {
+                start -= 1;
+            }
+            start..=end
+        })
+        .collect()
 }
 
 fn find_messages(
     resolver: &impl FileResolver,
+    filter: &Filter,
     value: MessageDescriptor,
 ) -> Result<IndexMap<String, Message>> {
     let descriptor = CombinedDescriptor::Message(value);
+    // Like the types referenced by fields, the input and output might be hidden as well.
+    if !filter.is_visible(&descriptor.parent_file(), descriptor.path()) {
+        return Ok(IndexMap::new());
+    }
+
     let mut messages = IndexMap::from_iter([(
         descriptor.full_name().to_owned(),
-        Message::new(resolver, &descriptor)?,
+        Message::new(resolver, filter, &descriptor)?,
     )]);
 
-    collect_deps(resolver, &mut messages, &descriptor)?;
+    collect_deps(resolver, filter, &mut messages, &descriptor)?;
 
     Ok(messages)
 }
 
 fn collect_deps(
     resolver: &impl FileResolver,
+    filter: &Filter,
     deps: &mut IndexMap<String, Message>,
     message: &CombinedDescriptor,
 ) -> Result<()> {
     for field in message.fields() {
+        if !filter.is_visible(&field.parent_file(), field.path()) {
+            continue;
+        }
+
         let field = match field.kind() {
             Kind::Message(m) if m.is_map_entry() => m.map_entry_value_field(),
             _ => field,
@@ -298,16 +1517,18 @@ fn collect_deps(
 
         assert!(!descriptor.is_map_entry());
 
-        if descriptor.included_in(message) {
+        if descriptor.included_in(message)
+            || !filter.is_visible(&descriptor.parent_file(), descriptor.path())
+        {
             continue;
         }
 
         deps.insert(
             descriptor.full_name().to_owned(),
-            Message::new(resolver, &descriptor)?,
+            Message::new(resolver, filter, &descriptor)?,
         );
 
-        collect_deps(resolver, deps, &descriptor)?;
+        collect_deps(resolver, filter, deps, &descriptor)?;
     }
 
     Ok(())
@@ -365,6 +1586,48 @@ impl CombinedDescriptor {
         }
     }
 
+    /// Get all oneofs of this message, including synthetic ones. Always empty for enums.
+    fn oneofs(&self) -> Box<dyn ExactSizeIterator<Item = OneofDescriptor> + '_> {
+        match self {
+            Self::Message(d) => Box::new(d.oneofs()),
+            Self::Enum(_) => Box::new(std::iter::empty()),
+        }
+    }
+
+    /// Get all extensions of this message, from the whole descriptor pool. Always empty for enums.
+    fn extensions(&self) -> Box<dyn ExactSizeIterator<Item = ExtensionDescriptor> + '_> {
+        match self {
+            Self::Message(d) => Box::new(d.extensions()),
+            Self::Enum(_) => Box::new(std::iter::empty()),
+        }
+    }
+
+    /// Get all values of this enum. Always empty for messages.
+    fn values(&self) -> Box<dyn ExactSizeIterator<Item = EnumValueDescriptor> + '_> {
+        match self {
+            Self::Message(_) => Box::new(std::iter::empty()),
+            Self::Enum(d) => Box::new(d.values()),
+        }
+    }
+
+    /// Collect the source paths of all fields, enum values and nested types, recursively.
+    fn nested_paths(&self, paths: &mut Vec<Vec<i32>>) {
+        match self {
+            Self::Message(d) => {
+                paths.extend(d.fields().map(|f| f.path().to_vec()));
+                for nested in d.child_messages() {
+                    paths.push(nested.path().to_vec());
+                    Self::Message(nested).nested_paths(paths);
+                }
+                for nested in d.child_enums() {
+                    paths.push(nested.path().to_vec());
+                    Self::Enum(nested).nested_paths(paths);
+                }
+            }
+            Self::Enum(d) => paths.extend(d.values().map(|v| v.path().to_vec())),
+        }
+    }
+
     /// Whether this message/enum is included in the given other descriptor.
     fn included_in(&self, other: &CombinedDescriptor) -> bool {
         let parent = match self {
@@ -382,20 +1645,7 @@ impl CombinedDescriptor {
 
     /// Whether this message/enum is marked as deprecated.
     fn deprecated(&self) -> bool {
-        match self {
-            CombinedDescriptor::Message(d) => d
-                .descriptor_proto()
-                .options
-                .as_ref()
-                .and_then(|o| o.deprecated)
-                .unwrap_or(false),
-            CombinedDescriptor::Enum(d) => d
-                .enum_descriptor_proto()
-                .options
-                .as_ref()
-                .and_then(|o| o.deprecated)
-                .unwrap_or(false),
-        }
+        filter::has_deprecated_option(self.parent_file().file_descriptor_proto(), self.path())
     }
 }
 
@@ -0,0 +1,386 @@
+//! Validation rules of fields, as defined by [protovalidate](https://protovalidate.com) with the
+//! `buf.validate.field` option, or its predecessor `protoc-gen-validate` with `validate.rules`.
+
+use indexmap::IndexMap;
+use itertools::Itertools;
+use protox::prost_reflect::{DynamicMessage, FieldDescriptor, ReflectMessage, Value};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Names of the field options that carry validation rules.
+const EXTENSIONS: &[&str] = &["buf.validate.field", "validate.rules"];
+
+/// Rules that take a boolean flag and describe a well-known string format.
+const FORMATS: &[(&str, &str)] = &[
+    ("address", "hostname or IP address"),
+    ("email", "email address"),
+    ("hostname", "hostname"),
+    ("ip", "IP address"),
+    ("ipv4", "IPv4 address"),
+    ("ipv6", "IPv6 address"),
+    ("ip_prefix", "IP prefix"),
+    ("ipv4_prefix", "IPv4 prefix"),
+    ("ipv6_prefix", "IPv6 prefix"),
+    ("ip_with_prefixlen", "IP address with prefix length"),
+    ("ipv4_with_prefixlen", "IPv4 address with prefix length"),
+    ("ipv6_with_prefixlen", "IPv6 address with prefix length"),
+    ("host_and_port", "host and port pair"),
+    ("uri", "URI"),
+    ("uri_ref", "URI reference"),
+    ("uuid", "UUID"),
+    ("tuuid", "UUID without dashes"),
+];
+
+/// Rules that limit the length of a value, as names of the exact, minimum and maximum length rules
+/// and the unit they count in. Without unit, it's characters for strings and bytes otherwise.
+const LENGTHS: &[(Option<&str>, &str, &str, Option<&str>)] = &[
+    (Some("len"), "min_len", "max_len", None),
+    (Some("len_bytes"), "min_bytes", "max_bytes", Some("bytes")),
+    (None, "min_items", "max_items", Some("items")),
+    (None, "min_pairs", "max_pairs", Some("entries")),
+];
+
+/// A single validation constraint of a field, which might combine several related rules like the
+/// minimum and maximum length.
+#[derive(JsonSchema, Serialize)]
+pub struct Constraint {
+    /// Human-readable description of the constraint, like `1–64 characters` or `must be ≥ 0`.
+    text: String,
+    /// Raw rules of the constraint, by their path in the rule definition, like `string.min_len`.
+    rules: IndexMap<String, serde_json::Value>,
+}
+
+impl Constraint {
+    fn new(text: String, rules: impl IntoIterator<Item = (String, serde_json::Value)>) -> Self {
+        Self {
+            text,
+            rules: rules.into_iter().collect(),
+        }
+    }
+}
+
+/// Parse the validation rules of the field, if it has any.
+pub fn parse(field: &FieldDescriptor) -> Vec<Constraint> {
+    let pool = field.parent_pool();
+    let options = field.options();
+    let mut constraints = Vec::new();
+
+    for name in EXTENSIONS {
+        let Some(extension) = pool.get_extension_by_name(name) else {
+            continue;
+        };
+        if !options.has_extension(&extension) {
+            continue;
+        }
+        if let Value::Message(rules) = options.get_extension(&extension).as_ref() {
+            field_rules(&mut constraints, "", rules);
+        }
+    }
+
+    constraints
+}
+
+/// Parse the top-level rules of a field, which consist of a few generic rules and the rules for
+/// the specific type of the field.
+fn field_rules(constraints: &mut Vec<Constraint>, prefix: &str, rules: &DynamicMessage) {
+    // List the `required` rule first, as it's the most important one.
+    let fields = rules
+        .fields()
+        .sorted_by_key(|(field, _)| field.name() != "required");
+
+    for (field, value) in fields {
+        let path = format!("{prefix}{}", field.name());
+
+        match (field.name(), value) {
+            ("required", Value::Bool(true)) => {
+                constraints.push(Constraint::new(
+                    "required".to_owned(),
+                    [(path, true.into())],
+                ));
+            }
+            ("cel", Value::List(rules)) => {
+                for rule in rules.iter().filter_map(Value::as_message) {
+                    let text = match rule_str(rule, "message") {
+                        Some(message) if !message.is_empty() => message,
+                        _ => format!(
+                            "must satisfy `{}`",
+                            rule_str(rule, "expression").unwrap_or_default()
+                        ),
+                    };
+                    constraints.push(Constraint::new(
+                        text,
+                        [(path.clone(), to_json(&Value::Message(rule.clone())))],
+                    ));
+                }
+            }
+            (kind, Value::Message(rules)) => type_rules(constraints, &path, kind, rules),
+            _ => {}
+        }
+    }
+}
+
+/// Parse the rules for a specific type, like `string` or `int32`.
+fn type_rules(constraints: &mut Vec<Constraint>, path: &str, kind: &str, rules: &DynamicMessage) {
+    let mut rules = rules
+        .fields()
+        .map(|(field, value)| (field.name().to_owned(), value.clone()))
+        .collect::<IndexMap<_, _>>();
+
+    let mut take = |names: &[&str]| {
+        names
+            .iter()
+            .filter_map(|name| rules.shift_remove_entry(*name))
+            .map(|(name, value)| (format!("{path}.{name}"), value))
+            .collect::<Vec<_>>()
+    };
+
+    for (exact, min, max, unit) in LENGTHS {
+        let found = take(&[exact.unwrap_or(""), min, max]);
+        let get = |name: &str| {
+            found
+                .iter()
+                .find(|(rule, _)| rule.rsplit('.').next() == Some(name))
+                .map(|(_, value)| display(value))
+        };
+        let unit = unit.unwrap_or(if kind == "string" {
+            "characters"
+        } else {
+            "bytes"
+        });
+
+        if let Some(text) = length(exact.and_then(get), get(min), get(max), unit) {
+            constraints.push(Constraint::new(text, json(found)));
+        }
+    }
+
+    let found = take(&["gt", "gte", "lt", "lte"]);
+    if !found.is_empty() {
+        let text = found
+            .iter()
+            .map(|(name, value)| {
+                let op = match name.rsplit('.').next() {
+                    Some("gt") => ">",
+                    Some("gte") => "≥",
+                    Some("lt") => "<",
+                    _ => "≤",
+                };
+                format!("{op} {}", display(value))
+            })
+            .join(" and ");
+        constraints.push(Constraint::new(format!("must be {text}"), json(found)));
+    }
+
+    for (name, value) in std::mem::take(&mut rules) {
+        let rule = format!("{path}.{name}");
+        let text = match (name.as_str(), &value) {
+            (_, Value::Bool(false)) => continue,
+            ("required", Value::Bool(true)) => "required".to_owned(),
+            ("const", value) => format!("must be `{}`", display(value)),
+            ("in", Value::List(values)) => format!("must be one of {}", code_list(values)),
+            ("not_in", Value::List(values)) => format!("must not be one of {}", code_list(values)),
+            ("pattern", value) => format!("must match `{}`", display(value)),
+            ("prefix", value) => format!("must start with `{}`", display(value)),
+            ("suffix", value) => format!("must end with `{}`", display(value)),
+            ("contains", value) => format!("must contain `{}`", display(value)),
+            ("not_contains", value) => format!("must not contain `{}`", display(value)),
+            ("defined_only", Value::Bool(true)) => "must be a defined enum value".to_owned(),
+            ("unique", Value::Bool(true)) => "items must be unique".to_owned(),
+            ("finite", Value::Bool(true)) => "must be finite".to_owned(),
+            ("lt_now", Value::Bool(true)) => "must be in the past".to_owned(),
+            ("gt_now", Value::Bool(true)) => "must be in the future".to_owned(),
+            ("within", value) => format!("must be within {} of now", display(value)),
+            ("items" | "keys" | "values", Value::Message(rules)) => {
+                let mut nested = Vec::new();
+                field_rules(&mut nested, &format!("{rule}."), rules);
+                let each = match name.as_str() {
+                    "items" => "each item",
+                    "keys" => "each key",
+                    _ => "each value",
+                };
+                constraints.extend(nested.into_iter().map(|c| Constraint {
+                    text: format!("{each}: {}", c.text),
+                    rules: c.rules,
+                }));
+                continue;
+            }
+            (name, Value::Bool(true)) => match FORMATS.iter().find(|(n, _)| *n == name) {
+                Some((_, format)) => format!("must be a valid {format}"),
+                None => name.to_owned(),
+            },
+            (name, value) => format!("{name}: {}", display(value)),
+        };
+
+        constraints.push(Constraint::new(text, [(rule, to_json(&value))]));
+    }
+}
+
+/// Describe a length constraint, from the exact, minimum and maximum length.
+fn length(
+    exact: Option<String>,
+    min: Option<String>,
+    max: Option<String>,
+    unit: &str,
+) -> Option<String> {
+    let unit = |count: &str| match (count, unit) {
+        ("1", "entries") => "entry",
+        ("1", unit) => unit.trim_end_matches('s'),
+        (_, unit) => unit,
+    };
+
+    match (exact, min, max) {
+        (Some(exact), _, _) => Some(format!("exactly {exact} {}", unit(&exact))),
+        (None, Some(min), Some(max)) => Some(format!("{min}–{max} {}", unit(&max))),
+        (None, Some(min), None) => Some(format!("at least {min} {}", unit(&min))),
+        (None, None, Some(max)) => Some(format!("at most {max} {}", unit(&max))),
+        (None, None, None) => None,
+    }
+}
+
+/// Get a string field of a rule message, like the message of a CEL rule.
+fn rule_str(rule: &DynamicMessage, name: &str) -> Option<String> {
+    rule.get_field_by_name(name)
+        .and_then(|value| value.as_str().map(str::to_owned))
+}
+
+fn code_list(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|v| format!("`{}`", display(v)))
+        .join(", ")
+}
+
+fn json(rules: Vec<(String, Value)>) -> Vec<(String, serde_json::Value)> {
+    rules
+        .into_iter()
+        .map(|(name, value)| (name, to_json(&value)))
+        .collect()
+}
+
+/// Format a rule value for humans, with durations and timestamps in their usual notation.
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Message(m) => {
+            let seconds = || {
+                m.get_field_by_name("seconds")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0)
+            };
+            let nanos = || {
+                m.get_field_by_name("nanos")
+                    .and_then(|v| v.as_i32())
+                    .unwrap_or(0)
+            };
+
+            match m.descriptor().full_name() {
+                "google.protobuf.Duration" => format!("{}{}s", seconds(), fraction(nanos())),
+                "google.protobuf.Timestamp" => timestamp(seconds(), nanos()),
+                _ => to_json(value).to_string(),
+            }
+        }
+        value => to_json(value).to_string(),
+    }
+}
+
+/// Format nanoseconds as fraction of a second, like `.25`. Empty if there are none.
+fn fraction(nanos: i32) -> String {
+    if nanos == 0 {
+        return String::new();
+    }
+
+    let fraction = format!("{:09}", nanos.unsigned_abs());
+    format!(".{}", fraction.trim_end_matches('0'))
+}
+
+/// Format seconds since the Unix epoch as RFC 3339 timestamp in UTC, like
+/// `2023-11-14T22:13:20Z`.
+fn timestamp(seconds: i64, nanos: i32) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // Civil date from the days since the epoch, as described in
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}{}Z",
+        time / 3_600,
+        time / 60 % 60,
+        time % 60,
+        fraction(nanos)
+    )
+}
+
+/// Convert a dynamic Protobuf value into JSON.
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(v) => (*v).into(),
+        Value::I32(v) | Value::EnumNumber(v) => (*v).into(),
+        Value::I64(v) => (*v).into(),
+        Value::U32(v) => (*v).into(),
+        Value::U64(v) => (*v).into(),
+        Value::F32(v) => f64::from(*v).into(),
+        Value::F64(v) => (*v).into(),
+        Value::String(v) => v.clone().into(),
+        Value::Bytes(v) => String::from_utf8_lossy(v).into_owned().into(),
+        Value::List(values) => values.iter().map(to_json).collect(),
+        Value::Map(values) => values
+            .iter()
+            .map(|(key, value)| (format!("{key:?}"), to_json(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        Value::Message(message) => message
+            .fields()
+            .map(|(field, value)| (field.name().to_owned(), to_json(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_timestamp() {
+        assert_eq!("1970-01-01T00:00:00Z", timestamp(0, 0));
+        assert_eq!(
+            "2023-11-14T22:13:20.5Z",
+            timestamp(1_700_000_000, 500_000_000)
+        );
+        assert_eq!("2024-02-29T00:00:00Z", timestamp(1_709_164_800, 0));
+        assert_eq!("1969-12-31T23:59:59Z", timestamp(-1, 0));
+    }
+
+    #[test]
+    fn describe_length() {
+        let n = |n: u32| Some(n.to_string());
+
+        assert_eq!(
+            Some("1–64 characters".to_owned()),
+            length(None, n(1), n(64), "characters")
+        );
+        assert_eq!(
+            Some("exactly 8 bytes".to_owned()),
+            length(n(8), n(1), None, "bytes")
+        );
+        assert_eq!(
+            Some("at most 10 items".to_owned()),
+            length(None, None, n(10), "items")
+        );
+        assert_eq!(
+            Some("at least 1 entry".to_owned()),
+            length(None, n(1), None, "entries")
+        );
+        assert_eq!(None, length(None, None, None, "characters"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Report of all deprecated elements across the whole API, to plan their removal.
+
+use protox::prost_reflect::{EnumDescriptor, FileDescriptor, MessageDescriptor};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::filter::{self, Filter};
+
+/// Path of the `deprecated` option within the options of a file.
+const FILE_DEPRECATED_PATH: &[i32] = &[8, 23];
+
+/// A deprecated element of the schema.
+#[derive(JsonSchema, Serialize)]
+pub struct Deprecation {
+    /// Kind of the element, being either `file`, `service`, `method`, `message`, `enum`, `field`,
+    /// `oneof`, `extension` or `enum_value`.
+    kind: &'static str,
+    /// Fully qualified name of the element, or the path of the schema file. Enum values are
+    /// qualified by the name of their enum, like `acme.users.v1.Role.ROLE_ADMIN`.
+    name: String,
+    /// Path of the schema file that defines the element.
+    file: String,
+    /// Line of the definition within the schema file, starting at 1.
+    line: usize,
+    /// Reason of the deprecation, from a `@deprecated` tag or a `Deprecated:` line in the comment
+    /// of the element. Empty if there is none.
+    reason: String,
+}
+
+/// Collects the deprecations of a single schema file.
+struct Collector<'a> {
+    file: &'a FileDescriptor,
+    filter: &'a Filter,
+    deprecations: Vec<Deprecation>,
+}
+
+impl Collector<'_> {
+    /// Add the element at `path` if it's deprecated with the `deprecated` option or a
+    /// `@deprecated` tag, and visible according to the filter.
+    fn add(&mut self, kind: &'static str, name: &str, path: &[i32]) {
+        // The comment of a file is attached to its `deprecated` option, but the option belongs to
+        // the file itself.
+        let option_path = if path == FILE_DEPRECATED_PATH {
+            &[][..]
+        } else {
+            path
+        };
+        let deprecated =
+            filter::has_deprecated_option(self.file.file_descriptor_proto(), option_path);
+
+        let location = self
+            .file
+            .file_descriptor_proto()
+            .source_code_info
+            .as_ref()
+            .and_then(|info| info.location.iter().find(|l| l.path == path));
+
+        let (description, tags) = location
+            .map(|l| super::description(l.leading_comments()))
+            .unwrap_or_default();
+
+        if !(deprecated || tags.deprecated()) || !self.filter.is_visible(self.file, path) {
+            return;
+        }
+
+        let reason = tags.deprecated_reason().map_or_else(
+            || reason(&description).unwrap_or_default().to_owned(),
+            str::to_owned,
+        );
+
+        self.deprecations.push(Deprecation {
+            kind,
+            name: name.to_owned(),
+            file: self.file.name().to_owned(),
+            line: location.map_or(1, |l| l.span[0] as usize + 1),
+            reason,
+        });
+    }
+
+    fn message(&mut self, value: &MessageDescriptor) {
+        if value.is_map_entry() || !self.filter.is_visible(self.file, value.path()) {
+            return;
+        }
+        self.add("message", value.full_name(), value.path());
+
+        for field in value.fields() {
+            self.add("field", field.full_name(), field.path());
+        }
+        for oneof in value.oneofs().filter(|o| !o.is_synthetic()) {
+            // Oneofs have no `deprecated` option and can only be deprecated with a tag.
+            self.add("oneof", oneof.full_name(), oneof.path());
+        }
+        for extension in value.child_extensions() {
+            self.add("extension", extension.full_name(), extension.path());
+        }
+        for nested in value.child_messages() {
+            self.message(&nested);
+        }
+        for nested in value.child_enums() {
+            self.enumeration(&nested);
+        }
+    }
+
+    fn enumeration(&mut self, value: &EnumDescriptor) {
+        if !self.filter.is_visible(self.file, value.path()) {
+            return;
+        }
+        self.add("enum", value.full_name(), value.path());
+
+        for variant in value.values() {
+            let name = format!("{}.{}", value.full_name(), variant.name());
+            self.add("enum_value", &name, variant.path());
+        }
+    }
+}
+
+/// Find all deprecated elements in the schema files, ordered by file and line. Elements that are
+/// hidden by the filter aren't part of the report.
+pub fn collect(files: &[FileDescriptor], filter: &Filter) -> Vec<Deprecation> {
+    let mut deprecations = Vec::new();
+
+    for file in files {
+        let mut collector = Collector {
+            file,
+            filter,
+            deprecations: Vec::new(),
+        };
+        collector.add("file", file.name(), FILE_DEPRECATED_PATH);
+
+        for service in file.services().filter(|s| filter.service(s)) {
+            collector.add("service", service.full_name(), service.path());
+
+            for method in service.methods().filter(|m| filter.method(m)) {
+                collector.add("method", method.full_name(), method.path());
+            }
+        }
+        for message in file.messages() {
+            collector.message(&message);
+        }
+        for value in file.enums() {
+            collector.enumeration(&value);
+        }
+        for extension in file.extensions() {
+            collector.add("extension", extension.full_name(), extension.path());
+        }
+
+        let mut found = collector.deprecations;
+        found.sort_by_key(|d| d.line);
+        deprecations.append(&mut found);
+    }
+
+    deprecations
+}
+
+/// Find the reason of a deprecation in a `Deprecated:` line of the description, as commonly used
+/// in Go and other code generators.
+fn reason(description: &str) -> Option<&str> {
+    description.lines().find_map(|line| {
+        let line = line.trim();
+        let rest = line
+            .strip_prefix("Deprecated:")
+            .or_else(|| line.strip_prefix("DEPRECATED:"))?;
+        Some(rest.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_reason() {
+        assert_eq!(
+            Some("use `name` instead."),
+            reason("Old name.\nDeprecated: use `name` instead.")
+        );
+        assert_eq!(None, reason("Nothing to see here."));
+    }
+}
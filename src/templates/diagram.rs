@@ -0,0 +1,248 @@
+//! Mermaid diagrams of the relationships between messages and enums, based on the types that are
+//! already collected for each method or package.
+
+use std::fmt::Write;
+
+use super::{Field, Message, Method};
+use crate::config::{self, DiagramKind};
+
+/// How many values a field can hold of the type it references.
+#[derive(Clone, Copy)]
+enum Cardinality {
+    /// Exactly one value, like required fields or enums without presence tracking.
+    One,
+    /// Zero or one value, like optional fields or singular message fields.
+    Optional,
+    /// Any number of values, like repeated and map fields.
+    Many,
+}
+
+impl Cardinality {
+    fn of(field: &Field, target: &Message) -> Self {
+        match field.label {
+            _ if field.map_key.is_some() => Self::Many,
+            Some("repeated") => Self::Many,
+            Some("optional") => Self::Optional,
+            None if target.kind == "message" => Self::Optional,
+            _ => Self::One,
+        }
+    }
+
+    /// Multiplicity at the end of a class diagram relation.
+    fn class(self) -> &'static str {
+        match self {
+            Self::One => "1",
+            Self::Optional => "0..1",
+            Self::Many => "0..*",
+        }
+    }
+
+    /// Right-hand side of an entity relationship.
+    fn er(self) -> &'static str {
+        match self {
+            Self::One => "||",
+            Self::Optional => "o|",
+            Self::Many => "o{",
+        }
+    }
+}
+
+/// Render a Mermaid diagram of the given types, with an edge for each field that references
+/// another one of the types. Type names are shortened if they belong to the `package`.
+///
+/// Only the first types up to the configured limit are part of the diagram, in the given order.
+pub fn render(config: &config::Diagrams, package: &str, types: &[(&str, &Message)]) -> String {
+    let limit = match config.max_types {
+        0 => types.len(),
+        max => max.min(types.len()),
+    };
+    let (shown, omitted) = types.split_at(limit);
+
+    let mut out = String::new();
+    match config.kind {
+        DiagramKind::Class => class_diagram(&mut out, package, shown),
+        DiagramKind::Er => er_diagram(&mut out, package, shown),
+    }
+
+    if !omitted.is_empty() {
+        let note = format!("{} more types not shown", omitted.len());
+        match config.kind {
+            DiagramKind::Class => writeln!(out, "  note \"{note}\""),
+            DiagramKind::Er => writeln!(out, "  %% {note}"),
+        }
+        .ok();
+    }
+
+    out.truncate(out.trim_end().len());
+    out
+}
+
+fn class_diagram(out: &mut String, package: &str, types: &[(&str, &Message)]) {
+    writeln!(out, "classDiagram").ok();
+
+    for (name, message) in types {
+        if message.kind == "message" && message.fields.is_empty() {
+            writeln!(out, "  class {}", id(package, name)).ok();
+            continue;
+        }
+
+        writeln!(out, "  class {} {{", id(package, name)).ok();
+        if message.kind == "enum" {
+            writeln!(out, "    <<enumeration>>").ok();
+        }
+        for field in &message.fields {
+            writeln!(out, "    +{} {}", member_type(package, field), field.name).ok();
+        }
+        writeln!(out, "  }}").ok();
+    }
+
+    for (name, field, target, cardinality) in edges(types) {
+        writeln!(
+            out,
+            "  {} --> \"{}\" {} : {}",
+            id(package, name),
+            cardinality.class(),
+            id(package, target),
+            field.name,
+        )
+        .ok();
+    }
+}
+
+fn er_diagram(out: &mut String, package: &str, types: &[(&str, &Message)]) {
+    writeln!(out, "erDiagram").ok();
+
+    for (name, message) in types {
+        let scalars = message
+            .fields
+            .iter()
+            .filter(|f| !types.iter().any(|(name, _)| *name == f.ty))
+            .collect::<Vec<_>>();
+
+        if scalars.is_empty() {
+            writeln!(out, "  {}", id(package, name)).ok();
+            continue;
+        }
+
+        writeln!(out, "  {} {{", id(package, name)).ok();
+        for field in scalars {
+            writeln!(out, "    {} {}", member_type(package, field), field.name).ok();
+        }
+        writeln!(out, "  }}").ok();
+    }
+
+    for (name, field, target, cardinality) in edges(types) {
+        writeln!(
+            out,
+            "  {} ||--{} {} : \"{}\"",
+            id(package, name),
+            cardinality.er(),
+            id(package, target),
+            field.name,
+        )
+        .ok();
+    }
+}
+
+/// Render a Mermaid sequence diagram of the requests and responses of a method between a client
+/// and the service, with loops for streamed messages.
+pub fn sequence(package: &str, service: &str, method: &Method) -> String {
+    let input = method
+        .input
+        .keys()
+        .next()
+        .map_or("", |name| short_name(package, name));
+    let output = method
+        .output
+        .keys()
+        .next()
+        .map_or("", |name| short_name(package, name));
+
+    let mut out = String::new();
+    writeln!(out, "sequenceDiagram").ok();
+    writeln!(out, "  participant C as Client").ok();
+    writeln!(out, "  participant S as {service}").ok();
+
+    match (method.client_streaming, method.server_streaming) {
+        (false, false) => {
+            writeln!(out, "  C->>S: {input}").ok();
+            writeln!(out, "  S-->>C: {output}").ok();
+        }
+        (true, false) => {
+            writeln!(out, "  loop stream of requests").ok();
+            writeln!(out, "    C->>S: {input}").ok();
+            writeln!(out, "  end").ok();
+            writeln!(out, "  S-->>C: {output}").ok();
+        }
+        (false, true) => {
+            writeln!(out, "  C->>S: {input}").ok();
+            writeln!(out, "  loop stream of responses").ok();
+            writeln!(out, "    S-->>C: {output}").ok();
+            writeln!(out, "  end").ok();
+        }
+        (true, true) => {
+            writeln!(out, "  par requests").ok();
+            writeln!(out, "    loop stream of requests").ok();
+            writeln!(out, "      C->>S: {input}").ok();
+            writeln!(out, "    end").ok();
+            writeln!(out, "  and responses").ok();
+            writeln!(out, "    loop stream of responses").ok();
+            writeln!(out, "      S-->>C: {output}").ok();
+            writeln!(out, "    end").ok();
+            writeln!(out, "  end").ok();
+        }
+    }
+
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// Find all fields that reference one of the other types.
+fn edges<'a>(
+    types: &'a [(&'a str, &'a Message)],
+) -> impl Iterator<Item = (&'a str, &'a Field, &'a str, Cardinality)> {
+    types.iter().flat_map(move |(name, message)| {
+        message.fields.iter().filter_map(move |field| {
+            let (target, target_message) = types.iter().find(|(t, _)| *t == field.ty)?;
+            let cardinality = Cardinality::of(field, target_message);
+            Some((*name, field, *target, cardinality))
+        })
+    })
+}
+
+/// Turn the full name of a type into a Mermaid identifier, without the package name if it's
+/// defined in the `package`.
+fn id(package: &str, full_name: &str) -> String {
+    short_name(package, full_name).replace('.', "_")
+}
+
+/// Strip the package name from the type name, if it's defined in the `package`.
+fn short_name<'a>(package: &str, full_name: &'a str) -> &'a str {
+    full_name
+        .strip_prefix(package)
+        .and_then(|name| name.strip_prefix('.'))
+        .unwrap_or(full_name)
+}
+
+/// Format the type of a field for the member list of a diagram node, avoiding spaces and angle
+/// brackets that Mermaid can't handle.
+fn member_type(package: &str, field: &Field) -> String {
+    let ty = id(package, &field.ty);
+    match (&field.map_key, field.label) {
+        (Some(key), _) => format!("map[{key}]{ty}"),
+        (None, Some("repeated")) => format!("{ty}[]"),
+        _ => ty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shorten_ids() {
+        assert_eq!("User_Address", id("acme.v1", "acme.v1.User.Address"));
+        assert_eq!("other_v1_User", id("acme.v1", "other.v1.User"));
+        assert_eq!("uint32", id("acme.v1", "uint32"));
+    }
+}
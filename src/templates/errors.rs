@@ -0,0 +1,238 @@
+//! Errors that a method can return, documented either as an `Errors:` list in the comment of the
+//! method or with a custom method option.
+
+use itertools::Itertools;
+use protox::prost_reflect::{DynamicMessage, Kind, MethodDescriptor, ReflectMessage, Value};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::normalize;
+use crate::config;
+
+/// Line that starts the list of errors in a method comment.
+const HEADING: &str = "Errors:";
+
+/// Canonical gRPC status codes, as defined in `google.rpc.Code`.
+const CODES: &[&str] = &[
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+/// An error that a method can return.
+#[derive(JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct MethodError {
+    /// Name of the status code, like `NOT_FOUND`.
+    code: String,
+    /// Numeric value of the status code, if it's one of the canonical gRPC codes.
+    number: Option<u32>,
+    /// Description of the situation the error is returned in.
+    description: String,
+}
+
+impl MethodError {
+    /// Normalize the description, which is placed in a table that can't contain any headings.
+    pub fn normalize(&mut self, config: &config::Comments) {
+        self.description = normalize::normalize(config, &self.description, 6);
+    }
+
+    fn new(code: &str, description: String) -> Self {
+        let code = code.trim().to_ascii_uppercase();
+        let number = CODES
+            .iter()
+            .position(|c| *c == code)
+            .and_then(|n| n.try_into().ok());
+
+        Self {
+            code,
+            number,
+            description,
+        }
+    }
+}
+
+/// Split the `Errors:` list off the description of a method.
+///
+/// The list starts with a line only containing `Errors:`, followed by list items in the form
+/// `- CODE: description`. Indented lines continue the description of the previous item, and the
+/// list ends at the first other line. Returns the description without the list and the parsed
+/// errors.
+pub fn split(description: &str) -> (String, Vec<MethodError>) {
+    let lines = description.lines().collect::<Vec<_>>();
+    let Some(start) = lines.iter().position(|line| line.trim() == HEADING) else {
+        return (description.to_owned(), Vec::new());
+    };
+
+    let mut errors = Vec::<MethodError>::new();
+    let mut end = start + 1;
+
+    for line in &lines[start + 1..] {
+        if let Some((code, text)) = list_item(line) {
+            errors.push(MethodError::new(code, text.to_owned()));
+        } else if line.starts_with(char::is_whitespace)
+            && !line.trim().is_empty()
+            && let Some(last) = errors.last_mut()
+        {
+            last.description.push(' ');
+            last.description.push_str(line.trim());
+        } else {
+            break;
+        }
+        end += 1;
+    }
+
+    if errors.is_empty() {
+        return (description.to_owned(), errors);
+    }
+
+    let before = lines[..start].join("\n");
+    let after = lines[end..].join("\n");
+    let description = [before.trim(), after.trim()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .join("\n\n");
+
+    (description, errors)
+}
+
+/// Parse a single `- CODE: description` list item.
+fn list_item(line: &str) -> Option<(&str, &str)> {
+    let item = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))?;
+    let (code, text) = item.split_once(':')?;
+    let code = code.trim().trim_matches('`');
+
+    let valid = !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    valid.then(|| (code, text.trim()))
+}
+
+/// Read the errors from the method option with the given full name.
+///
+/// The option can be a single message or a list of messages with a `code` and `description` (or
+/// `message`) field, or a message wrapping such a list. The code can be an enum like
+/// `google.rpc.Code`, a number or a string. If the option doesn't exist, there are no errors, and
+/// a warning is logged once while collecting the schema.
+pub fn parse_option(method: &MethodDescriptor, name: &str) -> Vec<MethodError> {
+    let pool = method.parent_pool();
+    let Some(extension) = pool.get_extension_by_name(name) else {
+        return Vec::new();
+    };
+
+    let options = method.options();
+    if !options.has_extension(&extension) {
+        return Vec::new();
+    }
+
+    let mut errors = Vec::new();
+    collect(&mut errors, options.get_extension(&extension).as_ref());
+    errors
+}
+
+/// Collect all error messages from an option value, recursively.
+fn collect(errors: &mut Vec<MethodError>, value: &Value) {
+    match value {
+        Value::List(values) => {
+            for value in values {
+                collect(errors, value);
+            }
+        }
+        Value::Message(message) => match code(message) {
+            Some(code) => {
+                let description = ["description", "message"]
+                    .iter()
+                    .find_map(|name| {
+                        message
+                            .get_field_by_name(name)
+                            .and_then(|v| v.as_str().map(str::to_owned))
+                    })
+                    .unwrap_or_default();
+                errors.push(MethodError::new(&code, description));
+            }
+            None => {
+                for (_, value) in message.fields() {
+                    collect(errors, value);
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Get the status code of an error message, if it has a `code` field.
+fn code(message: &DynamicMessage) -> Option<String> {
+    let field = message.descriptor().get_field_by_name("code")?;
+    let value = message.get_field(&field);
+
+    match (field.kind(), &*value) {
+        (Kind::Enum(kind), Value::EnumNumber(n)) => Some(
+            kind.get_value(*n)
+                .map_or_else(|| n.to_string(), |v| v.name().to_owned()),
+        ),
+        (_, Value::I32(n)) => Some(code_name(i64::from(*n))),
+        (_, Value::I64(n)) => Some(code_name(*n)),
+        (_, Value::U32(n)) => Some(code_name(i64::from(*n))),
+        (_, Value::String(code)) => Some(code.clone()),
+        _ => None,
+    }
+}
+
+/// Get the name of a canonical gRPC status code, or the plain number if it's unknown.
+fn code_name(number: i64) -> String {
+    usize::try_from(number)
+        .ok()
+        .and_then(|n| CODES.get(n))
+        .map_or_else(|| number.to_string(), |c| (*c).to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_errors() {
+        let (description, errors) = split(
+            "Get a user.\n\nErrors:\n- NOT_FOUND: The user doesn't exist.\n- `PERMISSION_DENIED`: \
+             The caller\n  can't read users.\n\nMore text.",
+        );
+
+        assert_eq!("Get a user.\n\nMore text.", description);
+        assert_eq!(
+            vec![
+                MethodError {
+                    code: "NOT_FOUND".to_owned(),
+                    number: Some(5),
+                    description: "The user doesn't exist.".to_owned(),
+                },
+                MethodError {
+                    code: "PERMISSION_DENIED".to_owned(),
+                    number: Some(7),
+                    description: "The caller can't read users.".to_owned(),
+                },
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn keep_other_lists() {
+        let text = "Errors:\nnone in particular";
+        assert_eq!((text.to_owned(), Vec::new()), split(text));
+    }
+}
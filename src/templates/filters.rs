@@ -0,0 +1,216 @@
+//! Filters and global functions that are available in all templates, in addition to the
+//! [builtin](https://docs.rs/minijinja/latest/minijinja/filters/index.html) ones of `minijinja`.
+
+use std::{path::Path, sync::Arc};
+
+use itertools::Itertools;
+use minijinja::{Error, State, Value};
+
+use super::{CURRENT_KEY, Site};
+
+/// Characters that have a special meaning in Markdown and are escaped by [`md_escape`].
+///
+/// Pipes are left alone, as they only have a special meaning in tables. See [`table_escape`] for
+/// those.
+const MARKDOWN_SPECIAL: &[char] = &['\\', '`', '*', '_', '[', ']', '<', '>', '#'];
+
+/// Turn the text into a URL-safe slug, for example to use it as heading anchor.
+pub fn slugify(s: String) -> String {
+    slug::slugify(s)
+}
+
+/// Escape all characters with a special meaning in Markdown, so the text renders literally.
+pub fn md_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if MARKDOWN_SPECIAL.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Prepare the text for use inside a Markdown table cell, by escaping pipes and turning line
+/// breaks into `<br>` tags.
+pub fn table_escape(s: &str) -> String {
+    s.trim().replace('|', "\\|").lines().join("<br>")
+}
+
+/// Wrap each line of the text at word boundaries, to not exceed the given width (defaults to
+/// 100). Words longer than the width are kept as is.
+pub fn wrap(s: &str, width: Option<usize>) -> String {
+    let width = width.unwrap_or(100);
+
+    s.lines()
+        .map(|line| {
+            let mut wrapped = String::with_capacity(line.len());
+            let mut current = 0;
+
+            for word in line.split_whitespace() {
+                if current > 0 && current + 1 + word.len() > width {
+                    wrapped.push('\n');
+                    current = 0;
+                } else if current > 0 {
+                    wrapped.push(' ');
+                    current += 1;
+                }
+
+                wrapped.push_str(word);
+                current += word.len();
+            }
+
+            wrapped
+        })
+        .join("\n")
+}
+
+/// Convert a package name into a path, by turning each segment into a directory. For example,
+/// `acme.users.v1` becomes `acme/users/v1`.
+pub fn package_path(s: &str) -> String {
+    s.replace('.', "/")
+}
+
+/// Create a filter that formats the type of a field, like `repeated string` or
+/// `map<string, User>`.
+///
+/// Message and enum types that are defined in the package being rendered are shortened to their
+/// name, other types are kept fully qualified.
+pub fn type_name(
+    site: Arc<Site>,
+) -> impl Fn(&State<'_, '_>, &Value) -> Result<String, Error> + Send + Sync + 'static {
+    move |state, field| {
+        let ty = field.get_attr("type")?;
+        let ty = short_name(&site, state, ty.as_str().unwrap_or_default());
+
+        let map_key = field.get_attr("map_key")?;
+        if let Some(key) = map_key.as_str() {
+            return Ok(format!("map<{key}, {ty}>"));
+        }
+
+        let label = field.get_attr("label")?;
+        Ok(match label.as_str() {
+            Some(label) => format!("{label} {ty}"),
+            None => ty.to_owned(),
+        })
+    }
+}
+
+/// Turn a path relative to the output directory into one relative to the file that is currently
+/// being rendered, like the `path` of a package when linking to it from a nested index page.
+pub fn relative(state: &State<'_, '_>, path: &str) -> String {
+    match current(state, "path").as_ref().and_then(Value::as_str) {
+        Some(current) => relative_path(current, path),
+        None => path.to_owned(),
+    }
+}
+
+/// Create the anchor for the documentation of a message or enum, from its fully qualified name.
+pub fn anchor(full_name: &str) -> String {
+    slug::slugify(full_name)
+}
+
+/// Create a function that turns the fully qualified name of a message or enum into a Markdown link
+/// to its documentation, which might be located in another file.
+///
+/// The type index of the `site` tells the file (relative to the output directory) that documents
+/// each type. Types not found in the index are returned as plain name.
+pub fn link_to(site: Arc<Site>) -> impl Fn(&State<'_, '_>, &str) -> String + Send + Sync + 'static {
+    move |state, full_name| {
+        let name = short_name(&site, state, full_name);
+        let Some(target) = site.types.get(full_name) else {
+            return name.to_owned();
+        };
+
+        let current = current(state, "path");
+        let file = match current.as_ref().and_then(Value::as_str) {
+            Some(current) if current == target.path => String::new(),
+            Some(current) => relative_path(current, &target.path),
+            None => target.path.clone(),
+        };
+
+        format!("[{name}]({file}#{})", target.anchor)
+    }
+}
+
+/// Get an attribute of the file that is currently being rendered.
+fn current(state: &State<'_, '_>, name: &str) -> Option<Value> {
+    state
+        .lookup(CURRENT_KEY)
+        .and_then(|current| current.get_attr(name).ok())
+        .filter(|value| !value.is_undefined() && !value.is_none())
+}
+
+/// Strip the package name from the type name, if the type is defined in the package currently
+/// being rendered. Types of nested packages, like `acme.users.v1.User` in the `acme.users`
+/// package, are kept fully qualified.
+fn short_name<'a>(site: &Site, state: &State<'_, '_>, full_name: &'a str) -> &'a str {
+    let Some(package) = current(state, "package") else {
+        return full_name;
+    };
+    let Some(package) = package.as_str() else {
+        return full_name;
+    };
+
+    let defined_here = site
+        .types
+        .get(full_name)
+        .is_some_and(|location| location.package == package);
+    if !defined_here {
+        return full_name;
+    }
+
+    full_name
+        .strip_prefix(package)
+        .and_then(|name| name.strip_prefix('.'))
+        .unwrap_or(full_name)
+}
+
+/// Get the path to the `to` file, relative to the directory of the `from` file. Both are
+/// relative to the output directory.
+pub(super) fn relative_path(from: &str, to: &str) -> String {
+    let from = Path::new(from).parent().unwrap_or(Path::new(""));
+    let to = Path::new(to);
+
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    from.components()
+        .skip(common)
+        .map(|_| "..")
+        .chain(
+            to.components()
+                .skip(common)
+                .filter_map(|c| c.as_os_str().to_str()),
+        )
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markdown() {
+        assert_eq!(r"a \<b\> \*c\*", md_escape("a <b> *c*"));
+        assert_eq!(r"a \| b<br>c", table_escape("a | b\nc\n"));
+    }
+
+    #[test]
+    fn wrap_words() {
+        assert_eq!("aaa bb\ncc\nd", wrap("aaa bb cc\nd", Some(6)));
+    }
+
+    #[test]
+    fn resolve_relative_path() {
+        assert_eq!("b.md", relative_path("a.md", "b.md"));
+        assert_eq!(
+            "../orders/v1.md",
+            relative_path("acme/users/v1.md", "acme/orders/v1.md")
+        );
+        assert_eq!("acme/v1.md", relative_path("index.md", "acme/v1.md"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Normalization of the Markdown in comments, so it fits into the generated documents. Each step
+//! is enabled separately in the `comments` configuration.
+
+use super::references::code_span;
+use crate::config;
+
+/// Markers that open and close fenced code blocks.
+const FENCES: &[&str] = &["```", "~~~"];
+
+/// Apply all enabled normalization steps to the comment `text`, which is placed below a heading
+/// of the given `level` in the document.
+pub fn normalize(config: &config::Comments, text: &str, level: usize) -> String {
+    let mut text = text.to_owned();
+
+    if config.fix_lists {
+        text = fix_lists(&text);
+    }
+    if config.reflow {
+        text = reflow(&text);
+    }
+    if config.demote_headings {
+        text = demote_headings(&text, level);
+    }
+    if config.escape_vue || config.escape_html {
+        text = escape(&text, config.escape_vue, config.escape_html);
+    }
+
+    text
+}
+
+/// A single line of a comment, classified by whether it belongs to a fenced code block.
+enum Line<'a> {
+    Text(&'a str),
+    Code(&'a str),
+}
+
+/// Split the text into lines and mark the lines of fenced code blocks, including the fences.
+fn lines(text: &str) -> Vec<Line<'_>> {
+    let mut fence = None;
+
+    text.lines()
+        .map(|line| {
+            let marker = FENCES.iter().find(|m| line.trim_start().starts_with(**m));
+
+            match (fence, marker) {
+                (None, Some(marker)) => fence = Some(marker),
+                (Some(open), Some(marker)) if open == marker => {
+                    fence = None;
+                    return Line::Code(line);
+                }
+                (None, None) => return Line::Text(line),
+                _ => {}
+            }
+
+            Line::Code(line)
+        })
+        .collect()
+}
+
+/// Parse an ATX heading into its level and title.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim_start();
+    let level = line.len() - line.trim_start_matches('#').len();
+    let title = &line[level..];
+
+    ((1..=6).contains(&level) && (title.is_empty() || title.starts_with(' ')))
+        .then(|| (level, title.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Shift all headings down, so the highest one is one level below the heading of the element.
+/// Headings that would end up below level 6 are turned into bold text.
+fn demote_headings(text: &str, level: usize) -> String {
+    let lines = lines(text);
+    let Some(min) = lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Text(line) => heading(line).map(|(level, _)| level),
+            Line::Code(_) => None,
+        })
+        .min()
+    else {
+        return text.to_owned();
+    };
+
+    let shift = (level + 1).saturating_sub(min);
+
+    lines
+        .into_iter()
+        .map(|line| match line {
+            Line::Text(line) => match heading(line) {
+                Some((level, title)) if level + shift > 6 => format!("**{title}**"),
+                Some((level, title)) => format!("{} {title}", "#".repeat(level + shift)),
+                None => line.to_owned(),
+            },
+            Line::Code(line) => line.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape Vue interpolations (`{{`) and raw `<` characters outside of code.
+fn escape(text: &str, vue: bool, html: bool) -> String {
+    let escape_plain = |plain: &str, out: &mut String| {
+        let mut plain = plain.to_owned();
+        if html {
+            plain = plain.replace('<', "&lt;");
+        }
+        if vue {
+            // Entities would be decoded before Vue compiles the page, so only `v-pre` helps.
+            plain = plain.replace("{{", "<span v-pre>{{</span>");
+        }
+        out.push_str(&plain);
+    };
+
+    lines(text)
+        .into_iter()
+        .map(|line| match line {
+            Line::Text(line) => {
+                let mut out = String::with_capacity(line.len());
+                let mut rest = line;
+
+                while let Some(start) = rest.find('`') {
+                    escape_plain(&rest[..start], &mut out);
+                    let len = code_span(&rest[start..]);
+                    out.push_str(&rest[start..start + len]);
+                    rest = &rest[start + len..];
+                }
+
+                escape_plain(rest, &mut out);
+                out
+            }
+            Line::Code(line) => line.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse a list item into its indentation, marker (like `-` or `1.`) and content.
+fn list_item(line: &str) -> Option<(usize, &str, &str)> {
+    let content = line.trim_start();
+    let indent = line.len() - content.len();
+
+    let digits = content.len()
+        - content
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    let marker_len = match content[digits..].chars().next()? {
+        '-' | '*' | '+' if digits == 0 => 1,
+        '.' | ')' if (1..=9).contains(&digits) => digits + 1,
+        _ => return None,
+    };
+
+    let (marker, rest) = content.split_at(marker_len);
+    (rest.is_empty() || rest.starts_with(' ')).then(|| (indent, marker, rest.trim_start()))
+}
+
+/// Indent nested list items and their continuation lines by the width of their parent's marker,
+/// as required by `CommonMark`, and separate lists from a preceding paragraph by an empty line.
+fn fix_lists(text: &str) -> String {
+    // Original indentation and content column of the enclosing list items.
+    let mut stack = Vec::<(usize, usize)>::new();
+    let mut out = Vec::<String>::new();
+
+    for line in lines(text) {
+        let line = match line {
+            Line::Text(line) => line,
+            Line::Code(line) => {
+                out.push(line.to_owned());
+                continue;
+            }
+        };
+
+        if line.trim().is_empty() {
+            out.push(String::new());
+            continue;
+        }
+
+        if let Some((indent, marker, content)) = list_item(line) {
+            if stack.is_empty() && out.last().is_some_and(|prev| !prev.is_empty()) {
+                out.push(String::new());
+            }
+            while stack.last().is_some_and(|(i, _)| *i >= indent) {
+                stack.pop();
+            }
+
+            let column = stack.last().map_or(0, |(_, column)| *column);
+            out.push(format!("{}{marker} {content}", " ".repeat(column)));
+            stack.push((indent, column + marker.len() + 1));
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        if indent == 0 {
+            stack.clear();
+            out.push(line.to_owned());
+            continue;
+        }
+
+        while stack.len() > 1 && stack.last().is_some_and(|(i, _)| *i >= indent) {
+            stack.pop();
+        }
+        match stack.last() {
+            Some((_, column)) => out.push(format!("{}{}", " ".repeat(*column), line.trim_start())),
+            None => out.push(line.to_owned()),
+        }
+    }
+
+    out.join("\n")
+}
+
+/// Join hard-wrapped lines of paragraphs and list items into single lines. Headings, tables,
+/// block quotes, HTML, code and explicit line breaks are kept as they are.
+fn reflow(text: &str) -> String {
+    let mut out = Vec::<String>::new();
+    let mut joinable = false;
+
+    for line in lines(text) {
+        let line = match line {
+            Line::Text(line) => line,
+            Line::Code(line) => {
+                out.push(line.to_owned());
+                joinable = false;
+                continue;
+            }
+        };
+
+        let trimmed = line.trim_start();
+        let block = trimmed.is_empty()
+            || heading(line).is_some()
+            || trimmed.starts_with(['>', '|', '<'])
+            || (line.starts_with("    ") && !joinable)
+            || ["---", "***", "___"].contains(&trimmed.trim_end());
+
+        if block {
+            out.push(line.to_owned());
+            joinable = false;
+        } else if list_item(line).is_some() {
+            out.push(line.to_owned());
+            joinable = true;
+        } else if joinable && let Some(last) = out.last_mut() {
+            if last.ends_with("  ") || last.ends_with('\\') {
+                out.push(line.to_owned());
+            } else {
+                last.push(' ');
+                last.push_str(trimmed);
+            }
+        } else {
+            out.push(line.to_owned());
+            joinable = true;
+        }
+    }
+
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demote() {
+        assert_eq!(
+            "#### Usage\ntext\n##### Details\n```\n# comment\n```",
+            demote_headings("# Usage\ntext\n## Details\n```\n# comment\n```", 3)
+        );
+        assert_eq!("**Usage**", demote_headings("## Usage ##", 6));
+        assert_eq!("#hashtag", demote_headings("#hashtag", 2));
+    }
+
+    #[test]
+    fn escape_outside_code() {
+        assert_eq!(
+            "List&lt;T> and <span v-pre>{{</span> name }} but `List<T>`",
+            escape("List<T> and {{ name }} but `List<T>`", true, true)
+        );
+    }
+
+    #[test]
+    fn indent_lists() {
+        assert_eq!(
+            "Options:\n\n- one\n  - nested\n    more\n- two\n\n1. first",
+            fix_lists("Options:\n- one\n - nested\n   more\n- two\n\n1. first")
+        );
+    }
+
+    #[test]
+    fn reflow_paragraphs() {
+        assert_eq!(
+            "A long line.\n\n- item that continues\n- next\n\n| a |\n| b |",
+            reflow("A long\nline.\n\n- item that\n  continues\n- next\n\n| a |\n| b |")
+        );
+        assert_eq!("break  \nhere", reflow("break  \nhere"));
+    }
+}
@@ -0,0 +1,350 @@
+//! References to messages and enums inside of comments, like `[CreateUserRequest]` or
+//! `acme.users.v1.User`, which are turned into links to their documentation.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    fmt::Write,
+};
+
+use protox::prost_reflect::DescriptorPool;
+
+use super::{TypeLocation, filters};
+
+/// A reference in a comment that doesn't point to any known type.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Unresolved {
+    /// Full name of the element whose comment contains the reference.
+    scope: String,
+    /// The reference as written in the comment.
+    reference: String,
+}
+
+impl fmt::Display for Unresolved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unresolved reference `{}` in the comment of `{}`",
+            self.reference, self.scope
+        )
+    }
+}
+
+/// Names of the schema elements that aren't messages or enums, collected once to look up each
+/// word of the comments quickly.
+pub struct KnownNames {
+    /// All package names.
+    packages: HashSet<String>,
+    /// Full names of all services, extensions and enum values.
+    elements: HashSet<String>,
+}
+
+impl KnownNames {
+    pub fn new(pool: &DescriptorPool) -> Self {
+        let packages = pool.files().map(|f| f.package_name().to_owned()).collect();
+
+        let elements = pool
+            .services()
+            .map(|s| s.full_name().to_owned())
+            .chain(pool.all_extensions().map(|e| e.full_name().to_owned()))
+            .chain(pool.all_enums().flat_map(|e| {
+                e.values()
+                    .map(|v| v.full_name().to_owned())
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+
+        Self { packages, elements }
+    }
+}
+
+/// Rewrites references in the comments of a single documentation file.
+pub struct Linker<'a> {
+    pool: &'a DescriptorPool,
+    names: &'a KnownNames,
+    types: &'a BTreeMap<String, TypeLocation>,
+    /// Path of the file being rendered, relative to the output directory.
+    path: &'a str,
+    /// All references that couldn't be resolved so far.
+    pub unresolved: Vec<Unresolved>,
+}
+
+impl<'a> Linker<'a> {
+    pub fn new(
+        pool: &'a DescriptorPool,
+        names: &'a KnownNames,
+        types: &'a BTreeMap<String, TypeLocation>,
+        path: &'a str,
+    ) -> Self {
+        Self {
+            pool,
+            names,
+            types,
+            path,
+            unresolved: Vec::new(),
+        }
+    }
+
+    /// Turn all references in the Markdown `text` into links. Names in brackets are resolved
+    /// relative to the `scope` like in the Protobuf schema, while plain names must be fully
+    /// qualified.
+    ///
+    /// Code blocks, code spans and existing links are left alone.
+    pub fn link(&mut self, scope: &str, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut fence = None;
+
+        for line in text.split_inclusive('\n') {
+            let marker = line.trim_start();
+            let marker = ["```", "~~~"].into_iter().find(|m| marker.starts_with(m));
+
+            match (fence, marker) {
+                (None, Some(marker)) => fence = Some(marker),
+                (Some(open), Some(marker)) if open == marker => fence = None,
+                (None, None) => {
+                    self.link_line(scope, line, &mut out);
+                    continue;
+                }
+                _ => {}
+            }
+            out.push_str(line);
+        }
+
+        out
+    }
+
+    /// Link a `@see` reference, which is a plain (possibly relative) type name. Anything else,
+    /// like a method name or URL, is returned unchanged.
+    pub fn link_see(&mut self, scope: &str, text: &str) -> String {
+        if text.starts_with('[') || !is_name(text) {
+            return self.link(scope, text);
+        }
+
+        self.resolve(scope, text)
+            .and_then(|full_name| self.href(&full_name))
+            .map_or_else(|| text.to_owned(), |href| format!("[{text}]({href})"))
+    }
+
+    fn link_line(&mut self, scope: &str, line: &str, out: &mut String) {
+        let mut rest = line;
+
+        while let Some(start) = rest.find(['`', '[', '<']) {
+            let (plain, special) = rest.split_at(start);
+            self.link_plain(scope, plain, out);
+
+            let len = match special.as_bytes()[0] {
+                b'`' => code_span(special),
+                b'<' => special.find('>').map_or(1, |end| end + 1),
+                _ => match self.link_brackets(scope, special, out) {
+                    Some(len) => {
+                        rest = &special[len..];
+                        continue;
+                    }
+                    None => existing_link(special),
+                },
+            };
+
+            out.push_str(&special[..len]);
+            rest = &special[len..];
+        }
+
+        self.link_plain(scope, rest, out);
+    }
+
+    /// Link a reference in brackets at the start of `text`, returning the length of the consumed
+    /// text if it was one.
+    fn link_brackets(&mut self, scope: &str, text: &str, out: &mut String) -> Option<usize> {
+        let end = text.find(']')?;
+        let name = &text[1..end];
+        let next = text[end + 1..].chars().next();
+
+        let candidate = is_name(name)
+            && (name.contains('.') || name.starts_with(|c: char| c.is_ascii_uppercase()))
+            && !matches!(next, Some('(' | '[' | ':'));
+        if !candidate {
+            return None;
+        }
+
+        let reference = &text[..=end];
+        let Some(full_name) = self.resolve(scope, name) else {
+            self.unresolved(scope, reference);
+            out.push_str(reference);
+            return Some(end + 1);
+        };
+
+        if let Some(href) = self.href(&full_name) {
+            write!(out, "[{name}]({href})").ok();
+        } else {
+            out.push_str(reference);
+        }
+
+        Some(end + 1)
+    }
+
+    /// Link fully qualified names in plain text.
+    fn link_plain(&mut self, scope: &str, text: &str, out: &mut String) {
+        let mut last = 0;
+        let mut chars = text.char_indices().peekable();
+        let mut prev = None;
+
+        while let Some((start, c)) = chars.next() {
+            let boundary = !prev.is_some_and(|p: char| {
+                p.is_alphanumeric() || matches!(p, '_' | '.' | '/' | ':' | '@' | '-')
+            });
+            prev = Some(c);
+
+            if !(boundary && (c.is_ascii_alphabetic() || c == '_')) {
+                continue;
+            }
+
+            let mut end = start + c.len_utf8();
+            while let Some((i, c)) =
+                chars.next_if(|(_, c)| c.is_alphanumeric() || matches!(c, '_' | '.'))
+            {
+                end = i + c.len_utf8();
+                prev = Some(c);
+            }
+
+            let name = text[start..end].trim_end_matches('.');
+            if !name.contains('.') {
+                continue;
+            }
+
+            if self.is_type(name) {
+                if let Some(href) = self.href(name) {
+                    out.push_str(&text[last..start]);
+                    write!(out, "[{name}]({href})").ok();
+                    last = start + name.len();
+                }
+            } else if !self.is_known(name) && self.in_known_package(name) {
+                self.unresolved(scope, name);
+            }
+        }
+
+        out.push_str(&text[last..]);
+    }
+
+    /// Resolve a type name relative to the `scope`, by searching the scope and all its parents,
+    /// like `protoc` does. Names with a leading dot are always fully qualified.
+    fn resolve(&self, scope: &str, name: &str) -> Option<String> {
+        if let Some(name) = name.strip_prefix('.') {
+            return self.is_type(name).then(|| name.to_owned());
+        }
+
+        let mut scope = scope;
+        loop {
+            let candidate = if scope.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{scope}.{name}")
+            };
+            if self.is_type(&candidate) {
+                return Some(candidate);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = scope.rsplit_once('.').map_or("", |(parent, _)| parent);
+        }
+    }
+
+    /// Create the link target for the type, if it's documented.
+    fn href(&self, full_name: &str) -> Option<String> {
+        let target = self.types.get(full_name)?;
+        let file = if target.path == self.path {
+            String::new()
+        } else {
+            filters::relative_path(self.path, &target.path)
+        };
+
+        Some(format!("{file}#{}", target.anchor))
+    }
+
+    fn is_type(&self, full_name: &str) -> bool {
+        self.pool.get_message_by_name(full_name).is_some()
+            || self.pool.get_enum_by_name(full_name).is_some()
+    }
+
+    /// Whether the name refers to anything else in the schema, like a package, service or the
+    /// member of a type.
+    fn is_known(&self, full_name: &str) -> bool {
+        let parent = full_name.rsplit_once('.').map_or("", |(parent, _)| parent);
+
+        self.names.elements.contains(full_name)
+            || self.names.packages.contains(full_name)
+            || self.is_type(parent)
+            || self.pool.get_service_by_name(parent).is_some()
+    }
+
+    /// Whether the name starts with a known package, which means it's most likely a misspelled
+    /// reference rather than an unrelated text with dots.
+    fn in_known_package(&self, full_name: &str) -> bool {
+        full_name
+            .match_indices('.')
+            .any(|(i, _)| self.names.packages.contains(&full_name[..i]))
+    }
+
+    fn unresolved(&mut self, scope: &str, reference: &str) {
+        self.unresolved.push(Unresolved {
+            scope: scope.to_owned(),
+            reference: reference.to_owned(),
+        });
+    }
+}
+
+/// Whether the text is a (possibly qualified) Protobuf identifier, like `acme.v1.User`.
+fn is_name(text: &str) -> bool {
+    let text = text.strip_prefix('.').unwrap_or(text);
+
+    !text.is_empty()
+        && text.split('.').all(|part| {
+            part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Get the length of the code span at the start of `text`, or the length of the opening
+/// backticks if the span isn't closed.
+pub(super) fn code_span(text: &str) -> usize {
+    let ticks = text.len() - text.trim_start_matches('`').len();
+    let delimiter = &text[..ticks];
+
+    text[ticks..]
+        .find(delimiter)
+        .map_or(ticks, |end| ticks + end + ticks)
+}
+
+/// Get the length of the existing link at the start of `text`, like `[text](url)`, or `1` to
+/// skip only the opening bracket if it isn't one.
+fn existing_link(text: &str) -> usize {
+    let Some(end) = text.find(']') else {
+        return 1;
+    };
+
+    match text[end + 1..].strip_prefix('(') {
+        Some(rest) => rest.find(')').map_or(1, |close| end + close + 3),
+        None => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_names() {
+        assert!(is_name("User"));
+        assert!(is_name(".acme.v1.User"));
+        assert!(!is_name("1st"));
+        assert!(!is_name("a..b"));
+        assert!(!is_name("see here"));
+    }
+
+    #[test]
+    fn skip_code_and_links() {
+        assert_eq!(6, code_span("`code` text"));
+        assert_eq!(2, code_span("``unclosed"));
+        assert_eq!(11, existing_link("[text](url) more"));
+        assert_eq!(1, existing_link("[text] more"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Structured tags in comments, like `@since v1.4` or `@deprecated use X instead`, that are
+//! separated from the prose of the description.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::{normalize, references::Linker};
+use crate::config;
+
+/// Names of all supported tags, without the leading `@`.
+const NAMES: &[&str] = &["since", "see", "example", "deprecated"];
+
+/// Structured tags of a comment.
+#[derive(Default, JsonSchema, Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Tags {
+    /// Version that introduced the element, from `@since`.
+    since: Option<String>,
+    /// References to related elements, from `@see`. The tag can be used several times.
+    see: Vec<String>,
+    /// Examples of using the element, from `@example` blocks.
+    examples: Vec<String>,
+    /// Reason of the deprecation, from `@deprecated`. Empty if the tag has no text.
+    deprecated: Option<String>,
+}
+
+impl Tags {
+    /// Whether the element is deprecated with a `@deprecated` tag.
+    pub fn deprecated(&self) -> bool {
+        self.deprecated.is_some()
+    }
+
+    /// Reason of the deprecation from the `@deprecated` tag, if it has any text.
+    pub fn deprecated_reason(&self) -> Option<&str> {
+        self.deprecated
+            .as_deref()
+            .filter(|reason| !reason.is_empty())
+    }
+
+    /// Turn references in the `@see` and `@deprecated` tags into links.
+    pub fn link(&mut self, linker: &mut Linker<'_>, scope: &str) {
+        for see in &mut self.see {
+            *see = linker.link_see(scope, see);
+        }
+        if let Some(reason) = &mut self.deprecated {
+            *reason = linker.link(scope, reason);
+        }
+    }
+
+    /// Normalize the text of all tags like descriptions, which are placed below a heading of the
+    /// given `level`. Examples are kept verbatim, as they're often code or payloads whose exact
+    /// formatting matters.
+    pub fn normalize(&mut self, config: &config::Comments, level: usize) {
+        let texts = self
+            .since
+            .iter_mut()
+            .chain(&mut self.see)
+            .chain(&mut self.deprecated);
+
+        for text in texts {
+            *text = normalize::normalize(config, text, level);
+        }
+    }
+
+    fn push(&mut self, name: &str, lines: &[&str]) {
+        let text = lines
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        match name {
+            "since" => self.since = Some(text),
+            "see" => self.see.push(text),
+            "deprecated" => self.deprecated = Some(text),
+            _ => self
+                .examples
+                .push(unindent::unindent(lines.join("\n").trim_matches('\n'))),
+        }
+    }
+}
+
+/// Split the tags off a description.
+///
+/// A tag starts with a line like `@since v1.4` and continues on the following lines up to the
+/// next empty line or tag. Only `@example` blocks can contain empty lines and continue up to the
+/// next tag or the end of the comment. Tags inside of fenced code blocks are ignored.
+pub fn split(description: &str) -> (String, Tags) {
+    let mut tags = Tags::default();
+    let mut prose = Vec::new();
+    let mut current: Option<(&str, Vec<&str>)> = None;
+    let mut fenced = false;
+    let mut found = false;
+
+    for line in description.lines() {
+        if !fenced && let Some((name, rest)) = tag(line) {
+            if let Some((name, lines)) = current.take() {
+                tags.push(name, &lines);
+            }
+            current = Some((name, vec![rest]));
+            found = true;
+            continue;
+        }
+
+        if let Some((name, lines)) = &mut current {
+            if *name == "example" || !line.trim().is_empty() {
+                lines.push(line);
+                continue;
+            }
+            tags.push(name, lines);
+            current = None;
+        }
+
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+        }
+        prose.push(line);
+    }
+
+    if let Some((name, lines)) = current {
+        tags.push(name, &lines);
+    }
+
+    if !found {
+        return (description.to_owned(), tags);
+    }
+
+    (collapse_blank_lines(&prose), tags)
+}
+
+/// Parse a tag line into the name of the tag and the text that follows it.
+fn tag(line: &str) -> Option<(&'static str, &str)> {
+    let line = line.trim_start().strip_prefix('@')?;
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let name = NAMES.iter().find(|n| **n == name)?;
+
+    Some((name, rest))
+}
+
+/// Join the lines, without the blank lines that are left over where tags were removed. Blank lines
+/// inside of fenced code blocks are kept.
+fn collapse_blank_lines(lines: &[&str]) -> String {
+    let mut text = String::new();
+    let mut blank = false;
+    let mut fenced = false;
+
+    for line in lines {
+        if !fenced && line.trim().is_empty() {
+            blank = !text.is_empty();
+            continue;
+        }
+        if blank {
+            text.push('\n');
+            blank = false;
+        }
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+
+    text.truncate(text.trim_end().len());
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_tags() {
+        let (description, tags) = split(
+            "Get a user.\n@since v1.4\n\nMore text.\n\n@see ListUsers\n@see acme.v1.User\n\
+             @deprecated use\nGetAccount instead.\n@example\n```json\n{}\n\n```",
+        );
+
+        assert_eq!("Get a user.\n\nMore text.", description);
+        assert_eq!(
+            Tags {
+                since: Some("v1.4".to_owned()),
+                see: vec!["ListUsers".to_owned(), "acme.v1.User".to_owned()],
+                examples: vec!["```json\n{}\n\n```".to_owned()],
+                deprecated: Some("use GetAccount instead.".to_owned()),
+            },
+            tags
+        );
+    }
+
+    #[test]
+    fn keep_fenced_blank_lines() {
+        let (description, _) = split("Text.\n@since v1\n\n```\na\n\n\nb\n```\n\n\nMore.");
+        assert_eq!("Text.\n\n```\na\n\n\nb\n```\n\nMore.", description);
+    }
+
+    #[test]
+    fn ignore_fenced_tags() {
+        let text = "```\n@since v1\n```\n@other tag";
+        assert_eq!((text.to_owned(), Tags::default()), split(text));
+    }
+}
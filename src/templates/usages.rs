@@ -0,0 +1,144 @@
+//! Reverse index from each message and enum to the methods that use it, to find the methods that
+//! are affected by a change to a type.
+
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexSet;
+use protox::prost_reflect::{FileDescriptor, Kind, MessageDescriptor, MethodDescriptor};
+
+use super::{Usage, filters};
+use crate::filter::Filter;
+
+/// Methods that use each message and enum, by the full name of the type.
+#[derive(Default)]
+pub struct Usages {
+    /// Methods that use the type directly as input or output.
+    direct: HashMap<String, Vec<Usage>>,
+    /// Methods that reach the type through their input or output, including through the fields
+    /// of other messages and nested types.
+    all: HashMap<String, Vec<Usage>>,
+}
+
+impl Usages {
+    /// Build the index from the methods and the path of the file that documents each of them.
+    ///
+    /// The types are found by walking the fields of the schema, regardless of any filters, as a
+    /// change to a type affects the methods even if parts of it aren't documented.
+    pub fn new(methods: impl IntoIterator<Item = (MethodDescriptor, String)>) -> Self {
+        let mut usages = Self::default();
+
+        for (method, path) in methods {
+            let service = method.parent_service();
+            let usage = Usage {
+                package: service.package_name().to_owned(),
+                service: service.name().to_owned(),
+                method: method.name().to_owned(),
+                anchor: format!(
+                    "{}-{}",
+                    filters::slugify(service.name().to_owned()),
+                    filters::slugify(method.name().to_owned())
+                ),
+                path,
+            };
+
+            let mut direct = IndexSet::new();
+            let mut all = IndexSet::new();
+            for message in [method.input(), method.output()] {
+                direct.insert(message.full_name().to_owned());
+                if all.insert(message.full_name().to_owned()) {
+                    reach(&message, &mut all);
+                }
+            }
+
+            for name in direct {
+                usages.direct.entry(name).or_default().push(usage.clone());
+            }
+            for name in all {
+                usages.all.entry(name).or_default().push(usage.clone());
+            }
+        }
+
+        usages
+    }
+
+    /// Methods that use the type directly as input or output.
+    pub fn direct(&self, full_name: &str) -> &[Usage] {
+        self.direct.get(full_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Methods that reach the type anywhere in their input or output.
+    pub fn all(&self, full_name: &str) -> &[Usage] {
+        self.all.get(full_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterate over the methods that reach each type, by the full name of the type.
+    pub(super) fn iter_all(&self) -> impl Iterator<Item = (&String, &Vec<Usage>)> {
+        self.all.iter()
+    }
+}
+
+/// Find the messages and enums of the files that can only be reached through methods that are
+/// filtered out of the documentation.
+///
+/// Types that no method uses at all are documented on their own, so everything they reach counts
+/// as used as well.
+pub fn filtered_types(files: &[FileDescriptor], filter: &Filter) -> HashSet<String> {
+    let (documented, filtered): (Vec<_>, Vec<_>) = files
+        .iter()
+        .flat_map(|file| file.services().collect::<Vec<_>>())
+        .flat_map(|service| service.methods().collect::<Vec<_>>())
+        .partition(|method| filter.service(method.parent_service()) && filter.method(method));
+
+    let reach_methods = |methods: Vec<MethodDescriptor>| {
+        let mut reached = IndexSet::new();
+        for method in methods {
+            for message in [method.input(), method.output()] {
+                if reached.insert(message.full_name().to_owned()) {
+                    reach(&message, &mut reached);
+                }
+            }
+        }
+        reached
+    };
+
+    let filtered = reach_methods(filtered);
+    let mut used = reach_methods(documented);
+
+    for file in files {
+        for message in file.messages() {
+            if !filtered.contains(message.full_name())
+                && used.insert(message.full_name().to_owned())
+            {
+                reach(&message, &mut used);
+            }
+        }
+        for value in file.enums() {
+            if !filtered.contains(value.full_name()) {
+                used.insert(value.full_name().to_owned());
+            }
+        }
+    }
+
+    filtered
+        .into_iter()
+        .filter(|name| !used.contains(name))
+        .collect()
+}
+
+/// Add all messages and enums that are reachable through the fields of the message. Each type is
+/// only walked once, to stop at recursive types.
+fn reach(message: &MessageDescriptor, reached: &mut IndexSet<String>) {
+    for field in message.fields() {
+        match field.kind() {
+            // Map entries are synthetic messages, so only their key and value types count.
+            Kind::Message(entry) if entry.is_map_entry() => reach(&entry, reached),
+            Kind::Message(message) if reached.insert(message.full_name().to_owned()) => {
+                reach(&message, reached);
+            }
+            Kind::Enum(value) => {
+                reached.insert(value.full_name().to_owned());
+            }
+            _ => {}
+        }
+    }
+}
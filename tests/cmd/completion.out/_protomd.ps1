@@ -25,11 +25,15 @@ Register-ArgumentCompleter -Native -CommandName 'protomd' -ScriptBlock {
             [CompletionResult]::new('--include', '--include', [CompletionResultType]::ParameterName, 'Directories that will be searched for referenced schema files')
             [CompletionResult]::new('-o', '-o', [CompletionResultType]::ParameterName, 'Directory to write the generated files into')
             [CompletionResult]::new('--output-dir', '--output-dir', [CompletionResultType]::ParameterName, 'Directory to write the generated files into')
+            [CompletionResult]::new('--audience', '--audience', [CompletionResultType]::ParameterName, 'Only document elements meant for the given audience')
             [CompletionResult]::new('--clean', '--clean', [CompletionResultType]::ParameterName, 'Remove any content from the output directory before writing any files to it. This is not done when the output directory points to the current directory')
             [CompletionResult]::new('-h', '-h', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
             [CompletionResult]::new('--help', '--help', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
             [CompletionResult]::new('init', 'init', [CompletionResultType]::ParameterValue, 'Initialize a new configuration file under the current working directory')
-            [CompletionResult]::new('templates', 'templates', [CompletionResultType]::ParameterValue, 'templates')
+            [CompletionResult]::new('templates', 'templates', [CompletionResultType]::ParameterValue, 'Write the embedded templates into a directory, as starting point for custom templates')
+            [CompletionResult]::new('usages', 'usages', [CompletionResultType]::ParameterValue, 'Print all methods that use the given message or enum on STDOUT')
+            [CompletionResult]::new('deprecations', 'deprecations', [CompletionResultType]::ParameterValue, 'Print all deprecated elements of the schema on STDOUT, as JSON')
+            [CompletionResult]::new('graph', 'graph', [CompletionResultType]::ParameterValue, 'Print the dependency graph of the schema on STDOUT, in the Graphviz DOT format')
             [CompletionResult]::new('schema', 'schema', [CompletionResultType]::ParameterValue, 'Print the schema of the template context on STDOUT')
             [CompletionResult]::new('completion', 'completion', [CompletionResultType]::ParameterValue, 'Create shell completion scripts for all supported shells')
             [CompletionResult]::new('manpages', 'manpages', [CompletionResultType]::ParameterValue, 'Create `man` page files with documentation about all options and subcommands')
@@ -46,6 +50,47 @@ Register-ArgumentCompleter -Native -CommandName 'protomd' -ScriptBlock {
             [CompletionResult]::new('--force', '--force', [CompletionResultType]::ParameterName, 'Force creating files if the target directory isn''t empty')
             [CompletionResult]::new('-h', '-h', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
             [CompletionResult]::new('--help', '--help', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            [CompletionResult]::new('check', 'check', [CompletionResultType]::ParameterValue, 'Check the configured templates for errors')
+            [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
+            break
+        }
+        'protomd;templates;check' {
+            [CompletionResult]::new('-h', '-h', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            [CompletionResult]::new('--help', '--help', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            break
+        }
+        'protomd;templates;help' {
+            [CompletionResult]::new('check', 'check', [CompletionResultType]::ParameterValue, 'Check the configured templates for errors')
+            [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
+            break
+        }
+        'protomd;templates;help;check' {
+            break
+        }
+        'protomd;templates;help;help' {
+            break
+        }
+        'protomd;usages' {
+            [CompletionResult]::new('-I', '-I ', [CompletionResultType]::ParameterName, 'Directories that will be searched for referenced schema files')
+            [CompletionResult]::new('--include', '--include', [CompletionResultType]::ParameterName, 'Directories that will be searched for referenced schema files')
+            [CompletionResult]::new('-h', '-h', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            [CompletionResult]::new('--help', '--help', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            break
+        }
+        'protomd;deprecations' {
+            [CompletionResult]::new('-I', '-I ', [CompletionResultType]::ParameterName, 'Directories that will be searched for referenced schema files')
+            [CompletionResult]::new('--include', '--include', [CompletionResultType]::ParameterName, 'Directories that will be searched for referenced schema files')
+            [CompletionResult]::new('-h', '-h', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            [CompletionResult]::new('--help', '--help', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            break
+        }
+        'protomd;graph' {
+            [CompletionResult]::new('-I', '-I ', [CompletionResultType]::ParameterName, 'Directories that will be searched for referenced schema files')
+            [CompletionResult]::new('--include', '--include', [CompletionResultType]::ParameterName, 'Directories that will be searched for referenced schema files')
+            [CompletionResult]::new('-p', '-p', [CompletionResultType]::ParameterName, 'Only show elements of packages matching the glob pattern, like `acme.users.*`. Can be given multiple times. All packages are shown by default')
+            [CompletionResult]::new('--package', '--package', [CompletionResultType]::ParameterName, 'Only show elements of packages matching the glob pattern, like `acme.users.*`. Can be given multiple times. All packages are shown by default')
+            [CompletionResult]::new('-h', '-h', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
+            [CompletionResult]::new('--help', '--help', [CompletionResultType]::ParameterName, 'Print help (see more with ''--help'')')
             break
         }
         'protomd;schema' {
@@ -65,7 +110,10 @@ Register-ArgumentCompleter -Native -CommandName 'protomd' -ScriptBlock {
         }
         'protomd;help' {
             [CompletionResult]::new('init', 'init', [CompletionResultType]::ParameterValue, 'Initialize a new configuration file under the current working directory')
-            [CompletionResult]::new('templates', 'templates', [CompletionResultType]::ParameterValue, 'templates')
+            [CompletionResult]::new('templates', 'templates', [CompletionResultType]::ParameterValue, 'Write the embedded templates into a directory, as starting point for custom templates')
+            [CompletionResult]::new('usages', 'usages', [CompletionResultType]::ParameterValue, 'Print all methods that use the given message or enum on STDOUT')
+            [CompletionResult]::new('deprecations', 'deprecations', [CompletionResultType]::ParameterValue, 'Print all deprecated elements of the schema on STDOUT, as JSON')
+            [CompletionResult]::new('graph', 'graph', [CompletionResultType]::ParameterValue, 'Print the dependency graph of the schema on STDOUT, in the Graphviz DOT format')
             [CompletionResult]::new('schema', 'schema', [CompletionResultType]::ParameterValue, 'Print the schema of the template context on STDOUT')
             [CompletionResult]::new('completion', 'completion', [CompletionResultType]::ParameterValue, 'Create shell completion scripts for all supported shells')
             [CompletionResult]::new('manpages', 'manpages', [CompletionResultType]::ParameterValue, 'Create `man` page files with documentation about all options and subcommands')
@@ -76,6 +124,19 @@ Register-ArgumentCompleter -Native -CommandName 'protomd' -ScriptBlock {
             break
         }
         'protomd;help;templates' {
+            [CompletionResult]::new('check', 'check', [CompletionResultType]::ParameterValue, 'Check the configured templates for errors')
+            break
+        }
+        'protomd;help;templates;check' {
+            break
+        }
+        'protomd;help;usages' {
+            break
+        }
+        'protomd;help;deprecations' {
+            break
+        }
+        'protomd;help;graph' {
             break
         }
         'protomd;help;schema' {
@@ -1,6 +1,6 @@
 # Print an optspec for argparse to handle cmd's options that are independent of any subcommand.
 function __fish_protomd_global_optspecs
-	string join /n I/include= o/output-dir= clean h/help
+	string join /n I/include= o/output-dir= clean audience= h/help
 end
 
 function __fish_protomd_needs_command
@@ -26,23 +26,43 @@ end
 
 complete -c protomd -n "__fish_protomd_needs_command" -s I -l include -d 'Directories that will be searched for referenced schema files' -r -f -a "(__fish_complete_directories)"
 complete -c protomd -n "__fish_protomd_needs_command" -s o -l output-dir -d 'Directory to write the generated files into' -r -f -a "(__fish_complete_directories)"
+complete -c protomd -n "__fish_protomd_needs_command" -l audience -d 'Only document elements meant for the given audience' -r
 complete -c protomd -n "__fish_protomd_needs_command" -l clean -d 'Remove any content from the output directory before writing any files to it. This is not done when the output directory points to the current directory'
 complete -c protomd -n "__fish_protomd_needs_command" -s h -l help -d 'Print help (see more with /'--help/')'
 complete -c protomd -n "__fish_protomd_needs_command" -a "init" -d 'Initialize a new configuration file under the current working directory'
-complete -c protomd -n "__fish_protomd_needs_command" -a "templates"
+complete -c protomd -n "__fish_protomd_needs_command" -a "templates" -d 'Write the embedded templates into a directory, as starting point for custom templates'
+complete -c protomd -n "__fish_protomd_needs_command" -a "usages" -d 'Print all methods that use the given message or enum on STDOUT'
+complete -c protomd -n "__fish_protomd_needs_command" -a "deprecations" -d 'Print all deprecated elements of the schema on STDOUT, as JSON'
+complete -c protomd -n "__fish_protomd_needs_command" -a "graph" -d 'Print the dependency graph of the schema on STDOUT, in the Graphviz DOT format'
 complete -c protomd -n "__fish_protomd_needs_command" -a "schema" -d 'Print the schema of the template context on STDOUT'
 complete -c protomd -n "__fish_protomd_needs_command" -a "completion" -d 'Create shell completion scripts for all supported shells'
 complete -c protomd -n "__fish_protomd_needs_command" -a "manpages" -d 'Create `man` page files with documentation about all options and subcommands'
 complete -c protomd -n "__fish_protomd_needs_command" -a "help" -d 'Print this message or the help of the given subcommand(s)'
 complete -c protomd -n "__fish_protomd_using_subcommand init" -s h -l help -d 'Print help (see more with /'--help/')'
-complete -c protomd -n "__fish_protomd_using_subcommand templates" -s f -l force -d 'Force creating files if the target directory isn/'t empty'
-complete -c protomd -n "__fish_protomd_using_subcommand templates" -s h -l help -d 'Print help (see more with /'--help/')'
+complete -c protomd -n "__fish_protomd_using_subcommand templates; and not __fish_seen_subcommand_from check help" -s f -l force -d 'Force creating files if the target directory isn/'t empty'
+complete -c protomd -n "__fish_protomd_using_subcommand templates; and not __fish_seen_subcommand_from check help" -s h -l help -d 'Print help (see more with /'--help/')'
+complete -c protomd -n "__fish_protomd_using_subcommand templates; and not __fish_seen_subcommand_from check help" -a "check" -d 'Check the configured templates for errors'
+complete -c protomd -n "__fish_protomd_using_subcommand templates; and not __fish_seen_subcommand_from check help" -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c protomd -n "__fish_protomd_using_subcommand templates; and __fish_seen_subcommand_from check" -s h -l help -d 'Print help (see more with /'--help/')'
+complete -c protomd -n "__fish_protomd_using_subcommand templates; and __fish_seen_subcommand_from help" -f -a "check" -d 'Check the configured templates for errors'
+complete -c protomd -n "__fish_protomd_using_subcommand templates; and __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c protomd -n "__fish_protomd_using_subcommand usages" -s I -l include -d 'Directories that will be searched for referenced schema files' -r -f -a "(__fish_complete_directories)"
+complete -c protomd -n "__fish_protomd_using_subcommand usages" -s h -l help -d 'Print help (see more with /'--help/')'
+complete -c protomd -n "__fish_protomd_using_subcommand deprecations" -s I -l include -d 'Directories that will be searched for referenced schema files' -r -f -a "(__fish_complete_directories)"
+complete -c protomd -n "__fish_protomd_using_subcommand deprecations" -s h -l help -d 'Print help (see more with /'--help/')'
+complete -c protomd -n "__fish_protomd_using_subcommand graph" -s I -l include -d 'Directories that will be searched for referenced schema files' -r -f -a "(__fish_complete_directories)"
+complete -c protomd -n "__fish_protomd_using_subcommand graph" -s p -l package -d 'Only show elements of packages matching the glob pattern, like `acme.users.*`. Can be given multiple times. All packages are shown by default' -r
+complete -c protomd -n "__fish_protomd_using_subcommand graph" -s h -l help -d 'Print help (see more with /'--help/')'
 complete -c protomd -n "__fish_protomd_using_subcommand schema" -s h -l help -d 'Print help (see more with /'--help/')'
 complete -c protomd -n "__fish_protomd_using_subcommand completion" -s h -l help -d 'Print help (see more with /'--help/')'
 complete -c protomd -n "__fish_protomd_using_subcommand manpages" -s h -l help -d 'Print help (see more with /'--help/')'
-complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates schema completion manpages help" -f -a "init" -d 'Initialize a new configuration file under the current working directory'
-complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates schema completion manpages help" -f -a "templates"
-complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates schema completion manpages help" -f -a "schema" -d 'Print the schema of the template context on STDOUT'
-complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates schema completion manpages help" -f -a "completion" -d 'Create shell completion scripts for all supported shells'
-complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates schema completion manpages help" -f -a "manpages" -d 'Create `man` page files with documentation about all options and subcommands'
-complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates schema completion manpages help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "init" -d 'Initialize a new configuration file under the current working directory'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "templates" -d 'Write the embedded templates into a directory, as starting point for custom templates'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "usages" -d 'Print all methods that use the given message or enum on STDOUT'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "deprecations" -d 'Print all deprecated elements of the schema on STDOUT, as JSON'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "graph" -d 'Print the dependency graph of the schema on STDOUT, in the Graphviz DOT format'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "schema" -d 'Print the schema of the template context on STDOUT'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "completion" -d 'Create shell completion scripts for all supported shells'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "manpages" -d 'Create `man` page files with documentation about all options and subcommands'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and not __fish_seen_subcommand_from init templates usages deprecations graph schema completion manpages help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c protomd -n "__fish_protomd_using_subcommand help; and __fish_seen_subcommand_from templates" -f -a "check" -d 'Check the configured templates for errors'